@@ -1,44 +1,88 @@
 use std::io::ErrorKind;
 use std::ops::Index;
 
-#[cfg(feature = "with_cargo")]
-use position::Player;
-#[cfg(feature = "with_cargo")]
-use location::Location;
-
-#[cfg(not(feature = "with_cargo"))]
-use src::position::Player;
-#[cfg(not(feature = "with_cargo"))]
-use src::location::Location;
-
+use crate::position::Player;
+use crate::location::Location;
 
 #[allow(dead_code)]
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum BoardValue{
+  #[default]
   Empty,
-  Filled(Player)
+  Filled(Player),
+  /// A hole in the board, nothing can be placed on it but links pass over it.
+  Blocked,
+  /// A stone of neither player, it blocks links of both.
+  Neutral,
 }
 
 impl BoardValue{
-  fn is_color(&self, player: &Player) -> bool{
+  pub(crate) fn is_color(&self, player: &Player) -> bool{
     match self {
-      BoardValue::Empty => false,
-      BoardValue::Filled(i) => i == player
+      BoardValue::Filled(i) => i == player,
+      _ => false
+    }
+  }
+
+  /// Returns whether this interrupts links of a player, see `Board::blocks`.
+  pub(crate) fn blocks(&self, player: &Player) -> bool {
+    match self {
+      BoardValue::Filled(i) => i != player,
+      BoardValue::Neutral => true,
+      _ => false
     }
   }
 }
 
-impl Default for BoardValue{
-  fn default() -> Self{
-    BoardValue::Empty
+/// Which sides of the board are joined together.
+/// A wrapped axis has to be at least twice as long as a link reaches, plus one,
+/// see `Position::check_rules`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Topology {
+  /// A plain rectangle.
+  #[default]
+  Flat,
+  /// The left and right side are joined, the x axis wraps around.
+  Cylinder,
+  /// Both pairs of opposite sides are joined.
+  Torus,
+}
+
+impl Topology {
+  /// Parses a topology as given on the command line.
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "flat" => Some(Topology::Flat),
+      "cylinder" => Some(Topology::Cylinder),
+      "torus" => Some(Topology::Torus),
+      _ => None
+    }
+  }
+
+  /// The name `from_name` parses.
+  pub fn name(self) -> &'static str {
+    match self {
+      Topology::Flat => "flat",
+      Topology::Cylinder => "cylinder",
+      Topology::Torus => "torus",
+    }
+  }
+
+  pub fn wraps_x(self) -> bool {
+    self != Topology::Flat
+  }
+
+  pub fn wraps_y(self) -> bool {
+    self == Topology::Torus
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Board {
   pub board: Vec<Vec<BoardValue>>,
   pub size_x: usize,
-  pub size_y: usize
+  pub size_y: usize,
+  pub topology: Topology,
 }
 
 impl Board{
@@ -46,12 +90,103 @@ impl Board{
   pub fn new(board: Vec<Vec<BoardValue>>) -> Self {
     Self {
       size_x: board.len(),
-      size_y: match board.get(0){
+      size_y: match board.first(){
         Some(i) => i.len(),
         None => 0,
       },
-      board
+      board,
+      topology: Topology::Flat,
+    }
+  }
+
+  /// Creates a board of the given size without any stones on it.
+  pub fn empty(size_x: usize, size_y: usize) -> Self {
+    Self {
+      size_x,
+      size_y,
+      board: vec![vec![BoardValue::default(); size_y]; size_x],
+      topology: Topology::Flat,
+    }
+  }
+
+  /// Returns this board with its sides joined according to a topology.
+  pub fn with_topology(self, topology: Topology) -> Self {
+    Self {
+      topology,
+      ..self
+    }
+  }
+
+  /// Returns whether the x and y axis wrap around. An axis without squares has nothing
+  /// to wrap around to, so it never does.
+  fn wraps(&self) -> (bool, bool) {
+    (
+      self.topology.wraps_x() && self.size_x > 0,
+      self.topology.wraps_y() && self.size_y > 0,
+    )
+  }
+
+  /// Moves a location that lies past a wrapped side back onto the board.
+  /// Locations past a side that does not wrap are left as they are.
+  pub fn wrap(&self, location: &Location) -> Location {
+    let (wraps_x, wraps_y) = self.wraps();
+    Location::new(
+      if wraps_x { location.x.rem_euclid(self.size_x as isize) } else { location.x },
+      if wraps_y { location.y.rem_euclid(self.size_y as isize) } else { location.y },
+    )
+  }
+
+  /// Returns the shortest offset from one square to another, which may go across a wrapped side.
+  pub fn delta(&self, from: &Location, to: &Location) -> Location {
+    fn shortest(delta: isize, size: usize, wraps: bool) -> isize {
+      let size = size as isize;
+      if wraps { (delta + size / 2).rem_euclid(size) - size / 2 } else { delta }
     }
+
+    let (wraps_x, wraps_y) = self.wraps();
+    Location::new(
+      shortest(to.x - from.x, self.size_x, wraps_x),
+      shortest(to.y - from.y, self.size_y, wraps_y),
+    )
+  }
+
+  /// Returns the straight lines a link between two squares is drawn as, in square
+  /// coordinates. A link across a wrapped side is split in two halves, each running
+  /// from its own stone to the side it leaves the board through, halfway between the
+  /// last square and the one past it.
+  pub fn link_segments(&self, from: &Location, to: &Location) -> Vec<((f64, f64), (f64, f64))> {
+    let point = |i: &Location| (i.x as f64, i.y as f64);
+    if !self.is_wrapped(from, to) {
+      return vec![(point(from), point(to))];
+    }
+
+    // how far along the line from a stone to a square past the side it still is on the board
+    let inside = |start: f64, end: f64, size: usize| {
+      let (low, high) = (-0.5, size as f64 - 0.5);
+      if end > high {
+        (high - start) / (end - start)
+      } else if end < low {
+        (low - start) / (end - start)
+      } else {
+        1.0
+      }
+    };
+
+    let delta = self.delta(from, to);
+    [(from, &delta), (to, &Location::new(-delta.x, -delta.y))]
+      .iter()
+      .map(|(start, delta)| {
+        let (x, y) = point(start);
+        let (end_x, end_y) = (x + delta.x as f64, y + delta.y as f64);
+        let t = inside(x, end_x, self.size_x).min(inside(y, end_y, self.size_y));
+        ((x, y), (x + (end_x - x) * t, y + (end_y - y) * t))
+      })
+      .collect()
+  }
+
+  /// Returns whether the shortest way between two squares goes across a wrapped side.
+  pub fn is_wrapped(&self, from: &Location, to: &Location) -> bool {
+    self.delta(from, to) != Location::new(to.x - from.x, to.y - from.y)
   }
 
   #[allow(dead_code)]
@@ -64,6 +199,15 @@ impl Board{
     (0..self.size_y).contains(&(location.y as usize))
   }
 
+  /// Returns whether a location is on the outermost ring of the board.
+  /// Wrapped sides are joined to the other side and so have no edge.
+  pub fn is_edge(&self, location: &Location) -> bool {
+    self.in_bounds(location) && (
+      !self.topology.wraps_x() && (location.x == 0 || location.x == self.size_x as isize - 1) ||
+      !self.topology.wraps_y() && (location.y == 0 || location.y == self.size_y as isize - 1)
+    )
+  }
+
   pub fn clear_at(&mut self, location: &Location) -> Result<(), ErrorKind> {
     if self.in_bounds(location) {
       self.board[location.x as usize][location.y as usize] = BoardValue::Empty;
@@ -82,6 +226,16 @@ impl Board{
       }
   }
 
+  /// Puts any kind of value on a square, for setting up holes and neutral stones.
+  pub fn set_at(&mut self, location: &Location, value: BoardValue) -> Result<(), ErrorKind> {
+    if self.in_bounds(location) {
+      self.board[location.x as usize][location.y as usize] = value;
+      Ok(())
+    } else {
+      Err(ErrorKind::InvalidInput)
+    }
+  }
+
   pub fn get_at(&self, location: &Location) -> Result<&BoardValue, ErrorKind>{
     if !self.in_bounds(location){
       Err(ErrorKind::InvalidInput)
@@ -99,22 +253,20 @@ impl Board{
     }
   }
 
+  /// Returns whether a stone on a location interrupts links of a player,
+  /// which is the case for stones of the opponent and neutral stones.
+  pub fn blocks(&self, location: &Location, player: &Player) -> bool {
+    self.get_at(location).is_ok_and(|i| i.blocks(player))
+  }
+
   pub fn proxy<'b>(&'b self, location: &'b Location) -> BoardProxy<'b>{
-    BoardProxy::new(self, &location)
+    BoardProxy::new(self, location)
   }
 }
 
 impl Default for Board{
   fn default() -> Self{
-
-      let size_x = 13;
-      let size_y = 13;
-
-      Self {
-        size_x: size_x,
-        size_y: size_y,
-        board: vec![vec![BoardValue::default(); size_x]; size_y],
-      }
+      Self::empty(13, 13)
   }
 }
 
@@ -124,20 +276,22 @@ impl std::fmt::Display for Board {
       for i in 0..self.size_x{
         write!(f, "{:2}", i)?;
       }
-      write!(f, "\n")?;
-      for x in 0..self.size_x{
-        write!(f, "{:3} ", x)?;
+      writeln!(f)?;
+      for y in 0..self.size_y{
+        write!(f, "{:3} ", y)?;
 
-        for y in 0..self.size_y{
-          match &self.board[y][x]{        
+        for x in 0..self.size_x{
+          match &self.board[x][y]{
             BoardValue::Empty => write!(f, "  ")?,
+            BoardValue::Blocked => write!(f, " #")?,
+            BoardValue::Neutral => write!(f, " N")?,
             BoardValue::Filled(i) => match i {
               Player::White => write!(f, " W")?,
               Player::Black => write!(f, " B")?
             }
           };
         }
-        write!(f, "\n")?;
+        writeln!(f)?;
       }
 
       Ok(())
@@ -166,23 +320,37 @@ impl<'b> BoardProxy<'b>{
     }
   }
 
+  /// Returns the square at an offset from the centre, wrapped onto the board.
   pub fn get_absolute(&self, location: &Location) -> Location {
-    location + self.center
+    self.board.wrap(&(location + self.center))
   }
 
   #[allow(dead_code)]
   pub fn get_at(&self, location: &Location) -> Result<&BoardValue, ErrorKind>{
-    let x = self.center.x + location.x;
-    let y = self.center.y + location.y;
-    
-    self.board.get_at(&Location::new(x,y))
+    self.board.get_at(&self.get_absolute(location))
   }
 
 
   pub fn is_color_at(&self, location: &Location, player: &Player) -> bool{
-    let x = self.center.x + location.x;
-    let y = self.center.y + location.y;
+    self.board.is_color_at(&self.get_absolute(location), player)
+  }
+
+  pub fn blocks(&self, location: &Location, player: &Player) -> bool{
+    self.board.blocks(&self.get_absolute(location), player)
+  }
+}
+#[cfg(test)]
+mod tests {
+  use crate::board::{Board, BoardValue};
+  use crate::location::Location;
+  use crate::position::Player;
 
-    self.board.is_color_at(&Location::new(x,y), player)
+  #[test]
+  fn test_display_rows() {
+    let mut board = Board::empty(3, 2);
+    board.insert_piece(&Player::Black, &Location::new(2, 0)).unwrap();
+    board.set_at(&Location::new(0, 1), BoardValue::Blocked).unwrap();
+
+    assert_eq!(board.to_string(), "    0 1 2\n  0      B\n  1  #    \n");
   }
-}
\ No newline at end of file
+}
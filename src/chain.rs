@@ -0,0 +1,311 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::position::Player;
+use crate::location::Location;
+
+/// Stones of one player that are linked to each other, as tracked by `Chains`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chain {
+  pub player: Player,
+  pub stones: HashSet<Location>,
+  /// How many of the stones are on an anchor square.
+  /// The chain is connected to an anchor as long as this is not zero.
+  pub anchors: usize,
+}
+
+impl Chain {
+  pub fn is_anchored(&self) -> bool {
+    self.anchors > 0
+  }
+}
+
+/// Keeps the stones on the board partitioned into chains while links are made and cut,
+/// so whether a stone is anchored can be looked up instead of searched for.
+///
+/// Making a link merges the smaller chain into the larger one. Cutting a link searches
+/// from both ends at the same time, so the work is bounded by the smaller of the two
+/// halves when the chain falls apart, or by the detour when it stays together.
+#[derive(Debug, Clone, Default)]
+pub struct Chains {
+  links: HashMap<Location, HashSet<Location>>,
+  anchor_stones: HashSet<Location>,
+  chain_of: HashMap<Location, usize>,
+  chains: HashMap<usize, Chain>,
+  next_id: usize,
+  /// How many stones the last `cut_link` visited, to check what cutting costs.
+  #[cfg(test)]
+  searched: usize,
+}
+
+/// One side of the search done when a link is cut.
+struct Search {
+  visited: HashSet<Location>,
+  stack: Vec<Location>,
+}
+
+enum Step {
+  Continue,
+  /// Ran into a stone the other side has already seen.
+  Met,
+  /// Nothing left to visit, the visited stones are cut off from the other side.
+  Exhausted,
+}
+
+impl Search {
+  fn new(start: &Location) -> Self {
+    Self {
+      visited: vec![start.to_owned()].into_iter().collect(),
+      stack: vec![start.to_owned()],
+    }
+  }
+
+  fn step(&mut self, links: &HashMap<Location, HashSet<Location>>, other: &Search) -> Step {
+    let current = match self.stack.pop() {
+      Some(i) => i,
+      None => return Step::Exhausted,
+    };
+
+    for next in &links[&current] {
+      if other.visited.contains(next) {
+        return Step::Met;
+      }
+      if self.visited.insert(next.to_owned()) {
+        self.stack.push(next.to_owned());
+      }
+    }
+
+    Step::Continue
+  }
+}
+
+impl Chains {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Returns the chain the stone at a location is part of.
+  pub fn get(&self, location: &Location) -> Option<&Chain> {
+    self.chain_of.get(location).map(|i| &self.chains[i])
+  }
+
+  /// Returns an identifier for the chain of a stone,
+  /// which stays the same until the chain is merged or split.
+  pub fn id_of(&self, location: &Location) -> Option<usize> {
+    self.chain_of.get(location).cloned()
+  }
+
+  pub fn chain(&self, id: usize) -> Option<&Chain> {
+    self.chains.get(&id)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = &Chain> {
+    self.chains.values()
+  }
+
+  /// Returns the stones a stone is linked to.
+  pub fn links_of(&self, location: &Location) -> impl Iterator<Item = &Location> {
+    self.links.get(location).into_iter().flatten()
+  }
+
+  pub fn is_linked(&self, a: &Location, b: &Location) -> bool {
+    self.links.get(a).is_some_and(|i| i.contains(b))
+  }
+
+  /// Adds a stone without any links as a chain of its own.
+  pub fn add_stone(&mut self, location: &Location, player: &Player, anchor: bool) {
+    let id = self.next_id;
+    self.next_id += 1;
+
+    if anchor {
+      self.anchor_stones.insert(location.to_owned());
+    }
+
+    self.links.insert(location.to_owned(), HashSet::new());
+    self.chain_of.insert(location.to_owned(), id);
+    self.chains.insert(id, Chain {
+      player: player.to_owned(),
+      stones: vec![location.to_owned()].into_iter().collect(),
+      anchors: anchor as usize,
+    });
+  }
+
+  /// Links two stones, merging their chains if they were not part of the same one yet.
+  pub fn add_link(&mut self, a: &Location, b: &Location) {
+    if !self.links.contains_key(a) || !self.links.contains_key(b) || self.is_linked(a, b) {
+      return;
+    }
+
+    self.links.get_mut(a).unwrap().insert(b.to_owned());
+    self.links.get_mut(b).unwrap().insert(a.to_owned());
+
+    let (keep, merge) = (self.chain_of[a], self.chain_of[b]);
+    if keep == merge {
+      return;
+    }
+
+    let (keep, merge) = if self.chains[&keep].stones.len() < self.chains[&merge].stones.len() {
+      (merge, keep)
+    } else {
+      (keep, merge)
+    };
+
+    let merged = self.chains.remove(&merge).unwrap();
+    for stone in &merged.stones {
+      self.chain_of.insert(stone.to_owned(), keep);
+    }
+
+    let chain = self.chains.get_mut(&keep).unwrap();
+    chain.anchors += merged.anchors;
+    chain.stones.extend(merged.stones);
+  }
+
+  /// Removes the link between two stones. When this splits their chain in two,
+  /// the smaller half gets a new identifier, which is returned.
+  pub fn cut_link(&mut self, a: &Location, b: &Location) -> Option<usize> {
+    if !self.is_linked(a, b) {
+      return None;
+    }
+
+    self.links.get_mut(a).unwrap().remove(b);
+    self.links.get_mut(b).unwrap().remove(a);
+
+    let mut from_a = Search::new(a);
+    let mut from_b = Search::new(b);
+
+    // which side ran out of stones to visit, if any
+    let exhausted = loop {
+      match from_a.step(&self.links, &from_b) {
+        Step::Met => break None,
+        Step::Exhausted => break Some(true),
+        Step::Continue => (),
+      }
+      match from_b.step(&self.links, &from_a) {
+        Step::Met => break None,
+        Step::Exhausted => break Some(false),
+        Step::Continue => (),
+      }
+    };
+
+    #[cfg(test)]
+    {
+      self.searched = from_a.visited.len() + from_b.visited.len();
+    }
+
+    let cut_off = match exhausted {
+      None => return None,
+      Some(true) => from_a.visited,
+      Some(false) => from_b.visited,
+    };
+
+    let old = self.chain_of[a];
+    let id = self.next_id;
+    self.next_id += 1;
+
+    let anchors = cut_off.intersection(&self.anchor_stones).count();
+    let chain = self.chains.get_mut(&old).unwrap();
+    chain.anchors -= anchors;
+    for stone in &cut_off {
+      chain.stones.remove(stone);
+      self.chain_of.insert(stone.to_owned(), id);
+    }
+
+    let player = chain.player.to_owned();
+    self.chains.insert(id, Chain {
+      player,
+      stones: cut_off,
+      anchors,
+    });
+
+    Some(id)
+  }
+
+  /// Removes a whole chain and all links between its stones.
+  pub fn remove_chain(&mut self, id: usize) -> Option<Chain> {
+    let chain = self.chains.remove(&id)?;
+
+    for stone in &chain.stones {
+      self.links.remove(stone);
+      self.chain_of.remove(stone);
+      self.anchor_stones.remove(stone);
+    }
+
+    Some(chain)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::chain::Chains;
+  use crate::position::Player;
+  use crate::location::Location;
+
+  #[test]
+  fn test_merge_and_split() {
+    let mut chains = Chains::new();
+    let stones: Vec<Location> = (0..5).map(|i| Location::new(i, 0)).collect();
+
+    chains.add_stone(&stones[0], &Player::Black, true);
+    for stone in &stones[1..] {
+      chains.add_stone(stone, &Player::Black, false);
+    }
+    for pair in stones.windows(2) {
+      chains.add_link(&pair[0], &pair[1]);
+    }
+    // close a loop, so cutting a single link keeps the chain together
+    chains.add_link(&stones[1], &stones[3]);
+
+    assert_eq!(chains.get(&stones[4]).unwrap().stones.len(), 5);
+    assert!(chains.get(&stones[4]).unwrap().is_anchored());
+
+    assert_eq!(chains.cut_link(&stones[2], &stones[3]), None);
+    assert_eq!(chains.id_of(&stones[0]), chains.id_of(&stones[4]));
+
+    let id = chains.cut_link(&stones[1], &stones[3]).unwrap();
+    assert_eq!(chains.id_of(&stones[3]), Some(id));
+    assert_eq!(chains.chain(id).unwrap().stones.len(), 2);
+    assert!(!chains.chain(id).unwrap().is_anchored());
+    assert_eq!(chains.get(&stones[0]).unwrap().anchors, 1);
+
+    let removed = chains.remove_chain(id).unwrap();
+    assert!(removed.stones.contains(&stones[4]));
+    assert!(chains.get(&stones[4]).is_none());
+    assert_eq!(chains.links_of(&stones[2]).count(), 1);
+  }
+
+  /// Builds a line of stones anchored at one end, with a loop of four stones
+  /// and a tail of two at the other end.
+  fn line(length: isize) -> Chains {
+    let mut chains = Chains::new();
+    for x in 0..length + 3 {
+      chains.add_stone(&Location::new(x, 0), &Player::Black, x == 0);
+    }
+    chains.add_stone(&Location::new(length, 1), &Player::Black, false);
+    for x in 0..length + 2 {
+      chains.add_link(&Location::new(x, 0), &Location::new(x + 1, 0));
+    }
+    chains.add_link(&Location::new(length - 2, 0), &Location::new(length, 1));
+    chains.add_link(&Location::new(length, 1), &Location::new(length, 0));
+    chains
+  }
+
+  #[test]
+  fn test_cut_cost() {
+    // finding everything again from the anchors would visit every stone, every time,
+    // while a cut only visits about as many stones as it cuts off or goes around
+    let mut costs = Vec::new();
+    for length in [100, 2000] {
+      let mut chains = line(length);
+
+      assert_eq!(chains.cut_link(&Location::new(length - 1, 0), &Location::new(length, 0)), None);
+      let detour = chains.searched;
+
+      let id = chains.cut_link(&Location::new(length, 0), &Location::new(length + 1, 0)).unwrap();
+      assert_eq!(chains.chain(id).unwrap().stones.len(), 2);
+      assert!(chains.get(&Location::new(length, 0)).unwrap().is_anchored());
+      costs.push((detour, chains.searched));
+    }
+
+    // the order of the search depends on hashing, so it can be off by a stone or two
+    assert!(costs.iter().all(|(detour, split)| *detour <= 8 && *split <= 8), "{:?}", costs);
+  }
+}
@@ -0,0 +1,277 @@
+use std::io::ErrorKind;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::log::Level;
+use crate::position::Player;
+
+/// How much time the players get.
+///
+/// As text, in seconds: `300` is sudden death, `300+5` adds 5 seconds after every move,
+/// `600+5x30` is followed by 5 byo-yomi periods of 30 seconds and `10/move` allows
+/// 10 seconds for every move.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimeControl {
+  /// A fixed amount of time for the whole game.
+  SuddenDeath { main: Duration },
+  /// Time for the whole game, with an increment added after every move.
+  Fischer { main: Duration, increment: Duration },
+  /// Main time, then periods that are used up only by moves that take longer than one period.
+  ByoYomi { main: Duration, periods: u32, period: Duration },
+  /// The same amount of time for every move, unused time is lost.
+  PerMove { time: Duration },
+}
+
+/// The time left for one player.
+#[derive(Debug, Clone, PartialEq)]
+struct Side {
+  remaining: Duration,
+  periods: u32,
+}
+
+/// The clocks of both players. Time is handed to it after every move,
+/// so it can be driven by a real clock as well as replayed from a record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameClock {
+  control: TimeControl,
+  black: Side,
+  white: Side,
+  flagged: Option<Player>,
+}
+
+impl GameClock {
+  pub fn new(control: TimeControl) -> Self {
+    let side = match &control {
+      TimeControl::SuddenDeath { main } | TimeControl::Fischer { main, .. } => Side {
+        remaining: *main,
+        periods: 0,
+      },
+      TimeControl::ByoYomi { main, periods, .. } => Side {
+        remaining: *main,
+        periods: *periods,
+      },
+      TimeControl::PerMove { time } => Side {
+        remaining: *time,
+        periods: 0,
+      },
+    };
+
+    Self {
+      control,
+      black: side.clone(),
+      white: side,
+      flagged: None,
+    }
+  }
+
+  pub fn control(&self) -> &TimeControl {
+    &self.control
+  }
+
+  /// Returns the main time a player has left, or the time for the move under `PerMove`.
+  pub fn remaining(&self, player: &Player) -> Duration {
+    self.side(player).remaining
+  }
+
+  /// Returns how many byo-yomi periods a player has left.
+  pub fn periods(&self, player: &Player) -> u32 {
+    self.side(player).periods
+  }
+
+  /// Returns the player who ran out of time, if any.
+  pub fn flagged(&self) -> Option<&Player> {
+    self.flagged.as_ref()
+  }
+
+  /// Takes the time a player spent on a move off their clock.
+  /// Fails with `TimedOut` when that was more than they had, or when a player already lost on time.
+  pub fn spend(&mut self, player: &Player, elapsed: Duration) -> Result<(), ErrorKind> {
+    if self.flagged.is_some() {
+      return Err(ErrorKind::TimedOut);
+    }
+
+    let control = self.control.clone();
+    let side = match player {
+      Player::Black => &mut self.black,
+      Player::White => &mut self.white,
+    };
+
+    let in_time = match control {
+      TimeControl::SuddenDeath { .. } => spend_main(side, elapsed).is_zero(),
+      TimeControl::Fischer { increment, .. } => {
+        let in_time = spend_main(side, elapsed).is_zero();
+        side.remaining += increment;
+        in_time
+      },
+      TimeControl::ByoYomi { period, .. } => {
+        let mut overtime = spend_main(side, elapsed);
+        // every period that passes in full is used up
+        while side.periods > 0 && overtime > period {
+          overtime -= period;
+          side.periods -= 1;
+        }
+        overtime.is_zero() || side.periods > 0
+      },
+      TimeControl::PerMove { time } => elapsed <= time,
+    };
+
+    if !in_time {
+      log!(Level::Info, "clock", "{:?} lost on time", player);
+      self.flagged = Some(player.to_owned());
+      return Err(ErrorKind::TimedOut);
+    }
+
+    Ok(())
+  }
+
+  fn side(&self, player: &Player) -> &Side {
+    match player {
+      Player::Black => &self.black,
+      Player::White => &self.white,
+    }
+  }
+}
+
+/// Takes time off the main time and returns how much more was spent than there was.
+fn spend_main(side: &mut Side, elapsed: Duration) -> Duration {
+  let overtime = elapsed.saturating_sub(side.remaining);
+  side.remaining = side.remaining.saturating_sub(elapsed);
+  overtime
+}
+
+/// Shows the time of both players, for example `Black 4:55  White 5:00`.
+impl std::fmt::Display for GameClock {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    for (i, player) in [Player::Black, Player::White].iter().enumerate() {
+      let side = self.side(player);
+      let seconds = side.remaining.as_secs();
+      write!(f, "{}{:?} {}:{:02}", if i == 0 { "" } else { "  " }, player, seconds / 60, seconds % 60)?;
+
+      if let TimeControl::ByoYomi { period, .. } = &self.control {
+        write!(f, " + {}x{}s", side.periods, period.as_secs())?;
+      }
+      if self.flagged.as_ref() == Some(player) {
+        write!(f, " (lost on time)")?;
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl std::fmt::Display for TimeControl {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      TimeControl::SuddenDeath { main } => write!(f, "{}", Seconds(*main)),
+      TimeControl::Fischer { main, increment } => write!(f, "{}+{}", Seconds(*main), Seconds(*increment)),
+      TimeControl::ByoYomi { main, periods, period } => {
+        write!(f, "{}+{}x{}", Seconds(*main), periods, Seconds(*period))
+      },
+      TimeControl::PerMove { time } => write!(f, "{}/move", Seconds(*time)),
+    }
+  }
+}
+
+impl FromStr for TimeControl {
+  type Err = ErrorKind;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    if let Some(time) = s.strip_suffix("/move") {
+      return Ok(TimeControl::PerMove { time: parse_seconds(time)? });
+    }
+
+    let (main, extra) = match s.split_once('+') {
+      Some((main, extra)) => (parse_seconds(main)?, extra),
+      None => return Ok(TimeControl::SuddenDeath { main: parse_seconds(s)? }),
+    };
+
+    match extra.split_once('x') {
+      Some((periods, period)) => Ok(TimeControl::ByoYomi {
+        main,
+        periods: periods.parse().map_err(|_| ErrorKind::InvalidData)?,
+        period: parse_seconds(period)?,
+      }),
+      None => Ok(TimeControl::Fischer { main, increment: parse_seconds(extra)? }),
+    }
+  }
+}
+
+/// Writes a duration in seconds, with milliseconds only when there are any.
+pub struct Seconds(pub Duration);
+
+impl std::fmt::Display for Seconds {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let millis = self.0.subsec_millis();
+    if millis == 0 {
+      write!(f, "{}", self.0.as_secs())
+    } else {
+      write!(f, "{}.{}", self.0.as_secs(), format!("{:03}", millis).trim_end_matches('0'))
+    }
+  }
+}
+
+/// Reads a number of seconds with at most three decimals, such as `12` or `0.25`.
+pub fn parse_seconds(s: &str) -> Result<Duration, ErrorKind> {
+  let (seconds, fraction) = s.split_once('.').unwrap_or((s, ""));
+  if seconds.is_empty() || fraction.len() > 3 || !s.chars().all(|i| i.is_ascii_digit() || i == '.') {
+    return Err(ErrorKind::InvalidData);
+  }
+
+  let seconds: u64 = seconds.parse().map_err(|_| ErrorKind::InvalidData)?;
+  let millis: u64 = format!("{:0<3}", fraction).parse().map_err(|_| ErrorKind::InvalidData)?;
+  Ok(Duration::from_secs(seconds) + Duration::from_millis(millis))
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::ErrorKind;
+  use std::time::Duration;
+
+  use crate::clock::{parse_seconds, GameClock, Seconds, TimeControl};
+  use crate::position::Player;
+
+  fn secs(s: u64) -> Duration {
+    Duration::from_secs(s)
+  }
+
+  #[test]
+  fn test_time_controls() {
+    let mut clock = GameClock::new("60+5".parse().unwrap());
+    clock.spend(&Player::Black, secs(20)).unwrap();
+    assert_eq!(clock.remaining(&Player::Black), secs(45));
+    assert_eq!(clock.to_string(), "Black 0:45  White 1:00");
+    assert_eq!(clock.spend(&Player::White, secs(61)), Err(ErrorKind::TimedOut));
+    assert_eq!(clock.flagged(), Some(&Player::White));
+    assert!(clock.spend(&Player::Black, secs(1)).is_err());
+
+    let mut clock = GameClock::new("10+2x5".parse().unwrap());
+    clock.spend(&Player::Black, secs(14)).unwrap();
+    assert_eq!(clock.periods(&Player::Black), 2);
+    clock.spend(&Player::Black, secs(7)).unwrap();
+    assert_eq!(clock.periods(&Player::Black), 1);
+    clock.spend(&Player::Black, secs(5)).unwrap();
+    assert!(clock.spend(&Player::Black, secs(6)).is_err());
+
+    let mut clock = GameClock::new("3/move".parse().unwrap());
+    clock.spend(&Player::Black, secs(3)).unwrap();
+    clock.spend(&Player::Black, secs(3)).unwrap();
+    assert!(clock.spend(&Player::White, secs(4)).is_err());
+
+    let mut clock = GameClock::new(TimeControl::SuddenDeath { main: secs(5) });
+    clock.spend(&Player::Black, secs(5)).unwrap();
+    assert!(clock.spend(&Player::Black, Duration::from_millis(1)).is_err());
+  }
+
+  #[test]
+  fn test_parse_time_controls() {
+    for text in &["300", "300+5", "600+5x30", "10/move", "0.5+0.25"] {
+      assert_eq!(text.parse::<TimeControl>().unwrap().to_string(), *text);
+    }
+    assert!("5+".parse::<TimeControl>().is_err());
+    assert!("-5".parse::<TimeControl>().is_err());
+
+    assert_eq!(parse_seconds("1.05").unwrap(), Duration::from_millis(1050));
+    assert!(parse_seconds("1.0005").is_err());
+    assert_eq!(Seconds(Duration::from_millis(1050)).to_string(), "1.05");
+  }
+}
@@ -0,0 +1,190 @@
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::str::FromStr;
+
+use crate::board::{Board, BoardValue};
+use crate::editor::Editor;
+use crate::location::Location;
+use crate::position::{Position, Player};
+use crate::rules::RuleSet;
+
+/// A position drawn as text, one row of the board per line, with the same glyphs as
+/// the ASCII renderer. Spaces are ignored, so its output can be pasted in as it is.
+///
+/// ```text
+/// B . + . .
+/// . . b . .
+/// W * . w #
+/// ```
+///
+/// `B` and `W` are stones, `N` a neutral stone and `#` a hole. Empty squares are `.`,
+/// or can be marked as legal for both (`+`), only black (`b`) or only white (`w`),
+/// or as the place of a captured stone (`*`). Markers only describe what a position
+/// is expected to look like, they do not change it.
+#[derive(Debug, Clone)]
+pub struct Diagram {
+  pub board: Board,
+  /// The stones of both players, row by row.
+  pub stones: Vec<(Location, Player)>,
+  pub legal_black_moves: HashSet<Location>,
+  pub legal_white_moves: HashSet<Location>,
+  pub captured: HashSet<Location>,
+}
+
+impl Diagram {
+  /// Whether any square is marked as legal. When one is, all of them should be,
+  /// and a `.` means that a square is legal for neither player.
+  pub fn marks_legal_moves(&self) -> bool {
+    !self.legal_black_moves.is_empty() || !self.legal_white_moves.is_empty()
+  }
+
+  /// Sets up the drawn position, played by the given rules.
+  /// Black is to move when both players have the same number of stones, otherwise white.
+  pub fn position(&self, rules: RuleSet) -> Result<Position, ErrorKind> {
+    let mut editor = Editor::new(self.board.clone(), rules);
+    for (location, player) in &self.stones {
+      editor.place(location, player)?;
+    }
+
+    let black = self.stones.iter().filter(|(_, i)| *i == Player::Black).count();
+    if black * 2 != self.stones.len() {
+      editor.set_turn(&Player::White);
+    }
+
+    editor.finish()
+  }
+}
+
+impl FromStr for Diagram {
+  type Err = ErrorKind;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let rows: Vec<Vec<char>> = s
+      .lines()
+      .map(|i| i.chars().filter(|i| !i.is_whitespace()).collect::<Vec<_>>())
+      .filter(|i| !i.is_empty())
+      .collect();
+
+    let size_x = rows.first().map_or(0, |i| i.len());
+    if size_x == 0 || rows.iter().any(|i| i.len() != size_x) {
+      return Err(ErrorKind::InvalidData);
+    }
+
+    let mut diagram = Diagram {
+      board: Board::empty(size_x, rows.len()),
+      stones: Vec::new(),
+      legal_black_moves: HashSet::new(),
+      legal_white_moves: HashSet::new(),
+      captured: HashSet::new(),
+    };
+
+    for (y, row) in rows.iter().enumerate() {
+      for (x, square) in row.iter().enumerate() {
+        let location = Location::new(x as isize, y as isize);
+
+        match square {
+          'B' => diagram.stones.push((location, Player::Black)),
+          'W' => diagram.stones.push((location, Player::White)),
+          'N' => diagram.board.set_at(&location, BoardValue::Neutral)?,
+          '#' => diagram.board.set_at(&location, BoardValue::Blocked)?,
+          '+' => {
+            diagram.legal_black_moves.insert(location.to_owned());
+            diagram.legal_white_moves.insert(location);
+          },
+          'b' => { diagram.legal_black_moves.insert(location); },
+          'w' => { diagram.legal_white_moves.insert(location); },
+          '*' => { diagram.captured.insert(location); },
+          '.' => (),
+          _ => return Err(ErrorKind::InvalidData),
+        }
+      }
+    }
+
+    Ok(diagram)
+  }
+}
+
+/// Builds a position with the default rules out of a diagram, one string per row,
+/// and panics when the diagram is not a valid position. See `Diagram` for the glyphs.
+///
+/// ```
+/// let position = connecticut::position![
+///   "B . . . ."
+///   ". . B . ."
+///   ". . . . W"
+/// ];
+/// assert_eq!(position.group(&connecticut::Location::new(2, 1)).unwrap().len(), 2);
+/// ```
+#[macro_export]
+macro_rules! position {
+  ($($row:literal)+) => {
+    $crate::Position::from_diagram(concat!($($row, "\n"),+)).expect("invalid position diagram")
+  };
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashSet;
+
+  use crate::diagram::Diagram;
+  use crate::location::Location;
+  use crate::position::{Position, Player};
+  use crate::render::RenderMode;
+  use crate::rules::RuleSet;
+
+  #[test]
+  fn test_diagram() {
+    let before: Diagram = "
+      B W . . . .
+      . . B . . .
+      . . . . . .
+      . . . . . .
+      . . . . . .
+    ".parse().unwrap();
+
+    assert_eq!(before.board.size_x, 6);
+    assert_eq!(before.board.size_y, 5);
+    assert!(!before.marks_legal_moves());
+
+    let mut position = before.position(RuleSet::default()).unwrap();
+    assert_eq!(position.turn, Player::White);
+
+    let after: Diagram = "
+      B W + + + +
+      W . * w . +
+      + . w . . +
+      + w . . . +
+      + + + + + +
+    ".parse().unwrap();
+
+    let outcome = position.make_move(Location::new(0, 1)).unwrap();
+    assert_eq!(outcome.captured_stones().cloned().collect::<HashSet<_>>(), after.captured);
+    assert_eq!(position.legal_black_moves, after.legal_black_moves);
+    assert_eq!(position.legal_white_moves, after.legal_white_moves);
+
+    // what the ASCII renderer draws can be read back
+    let drawn = position.render(RenderMode::Ascii).to_string();
+    let rows: Vec<&str> = drawn.lines().skip(1).take(5).map(|i| &i[3..]).collect();
+    let redrawn: Diagram = rows.join("\n").parse().unwrap();
+    assert_eq!(redrawn.stones, after.stones);
+    assert_eq!(redrawn.legal_white_moves, after.legal_white_moves);
+
+    assert!("B .\n. . .".parse::<Diagram>().is_err());
+    assert!("B x".parse::<Diagram>().is_err());
+    assert!("".parse::<Diagram>().is_err());
+  }
+
+  #[test]
+  fn test_position_macro() {
+    let position = position![
+      "B . . ."
+      ". . . ."
+      ". . . ."
+      ". B . ."
+    ];
+    assert!(position.group(&Location::new(1, 3)).is_ok());
+
+    // a stone away from the edge would already have been captured
+    assert!(Position::from_diagram(". . . . .\n. . B . .\n. . . . .").is_err());
+  }
+}
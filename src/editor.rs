@@ -0,0 +1,205 @@
+use std::collections::HashSet;
+use std::io::ErrorKind;
+
+use crate::board::{Board, BoardValue};
+use crate::location::Location;
+use crate::log::Level;
+use crate::position::{Position, Player};
+use crate::rules::{Capture, RuleSet};
+
+/// Sets up a position by placing and removing stones instead of playing moves.
+///
+/// Nothing is checked while editing. `finish` links the placed stones as if they were
+/// played after the stones already there, works out the chains and legal squares again
+/// and rejects positions that could not come up in a game.
+#[derive(Debug, Clone)]
+pub struct Editor {
+  position: Position,
+  /// The links of the position being edited, kept for as long as both of their stones are.
+  links: Vec<(Location, Location)>,
+  /// The stones placed while editing, in the order they are linked in.
+  placed: Vec<Location>,
+  legal_black_moves: Option<HashSet<Location>>,
+  legal_white_moves: Option<HashSet<Location>>,
+}
+
+impl Editor {
+  /// Starts editing an empty position on a board, which may have holes and neutral stones.
+  pub fn new(board: Board, rules: RuleSet) -> Self {
+    Position::new_unchecked(board, rules).edit()
+  }
+
+  /// Starts editing an existing position, keeping the links its stones already have,
+  /// so that finishing without any changes gives back the same position.
+  pub(crate) fn from_position(position: Position) -> Self {
+    Self {
+      links: position.chain_links(),
+      placed: Vec::new(),
+      position,
+      legal_black_moves: None,
+      legal_white_moves: None,
+    }
+  }
+
+  /// Puts a stone on a square, replacing any stone already there.
+  /// Fails for squares outside the board, holes and neutral stones.
+  pub fn place(&mut self, location: &Location, player: &Player) -> Result<(), ErrorKind> {
+    match self.position.board.get_at(location)? {
+      BoardValue::Empty | BoardValue::Filled(_) => (),
+      _ => return Err(ErrorKind::InvalidInput),
+    }
+
+    self.forget(location);
+    self.placed.push(location.to_owned());
+    self.position.board.insert_piece(player, location)
+  }
+
+  /// Takes a stone off the board. Fails when there is no stone on the square.
+  pub fn remove(&mut self, location: &Location) -> Result<(), ErrorKind> {
+    match self.position.board.get_at(location)? {
+      BoardValue::Filled(_) => (),
+      _ => return Err(ErrorKind::InvalidInput),
+    }
+
+    self.forget(location);
+    self.position.board.clear_at(location)
+  }
+
+  /// Drops everything known about the stone on a square, before it is replaced or removed.
+  fn forget(&mut self, location: &Location) {
+    self.placed.retain(|i| i != location);
+    self.links.retain(|(from, to)| from != location && to != location);
+  }
+
+  pub fn set_turn(&mut self, player: &Player) {
+    self.position.turn = player.to_owned();
+  }
+
+  /// Uses the given squares as the legal moves of a player,
+  /// instead of the ones that follow from the stones.
+  pub fn set_legal_moves(&mut self, player: &Player, moves: HashSet<Location>) {
+    match player {
+      Player::Black => self.legal_black_moves = Some(moves),
+      Player::White => self.legal_white_moves = Some(moves),
+    }
+  }
+
+  /// Returns the edited position with everything derived from the stones recomputed.
+  /// Fails with `InvalidInput` when the rules can't be played on the board,
+  /// and with `InvalidData` when a chain would already have been captured,
+  /// or when custom legal moves contain a square that is not empty.
+  pub fn finish(self) -> Result<Position, ErrorKind> {
+    let mut position = self.position;
+    Position::check_rules(&position.board, position.rules())?;
+    position.rebuild(&self.links, &self.placed)?;
+
+    if position.rules().capture == Capture::Unanchored {
+      for stone in position.groups().iter().flat_map(|i| i.stones.iter()) {
+        if !position.is_anchored(stone)? {
+          log!(Level::Debug, "editor", "rejected position, the chain of {} has no anchor", stone);
+          return Err(ErrorKind::InvalidData);
+        }
+      }
+    }
+
+    for (player, moves) in [(Player::Black, self.legal_black_moves), (Player::White, self.legal_white_moves)] {
+      let moves = match moves {
+        Some(i) => i,
+        None => continue,
+      };
+
+      if let Some(i) = moves.iter().find(|i| position.board.get_at(i) != Ok(&BoardValue::Empty)) {
+        log!(Level::Debug, "editor", "rejected legal moves for {:?}, {} is not an empty square", player, i);
+        return Err(ErrorKind::InvalidData);
+      }
+
+      match player {
+        Player::Black => position.legal_black_moves = moves,
+        Player::White => position.legal_white_moves = moves,
+      }
+    }
+
+    Ok(position)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashSet;
+  use std::io::ErrorKind;
+
+  use crate::board::{Board, BoardValue};
+  use crate::editor::Editor;
+  use crate::location::Location;
+  use crate::position::{Position, Player};
+  use crate::rules::{Blocking, Capture, RuleSet};
+
+  #[test]
+  fn test_editor() {
+    let mut editor = Editor::new(Board::default(), RuleSet::default());
+    editor.place(&Location::new(0, 0), &Player::Black).unwrap();
+    editor.place(&Location::new(2, 1), &Player::Black).unwrap();
+    editor.place(&Location::new(1, 0), &Player::White).unwrap();
+    editor.set_turn(&Player::White);
+
+    let mut position = editor.finish().unwrap();
+
+    // the same as after playing the moves
+    let mut played = Position::default();
+    for (x, y) in &[(0, 0), (1, 0), (2, 1)] {
+      played.make_move(Location::new(*x, *y)).unwrap();
+    }
+    assert_eq!(position.legal_black_moves, played.legal_black_moves);
+    assert_eq!(position.legal_white_moves, played.legal_white_moves);
+    assert_eq!(position.group(&Location::new(2, 1)).unwrap().len(), 2);
+
+    position.make_move(Location::new(0, 1)).unwrap();
+    assert_eq!(position.board.get_at(&Location::new(2, 1)).unwrap(), &BoardValue::Empty);
+
+    // editing it again, a black stone away from the edge has nothing to anchor it
+    let mut editor = position.edit();
+    editor.remove(&Location::new(0, 0)).unwrap();
+    assert!(editor.remove(&Location::new(0, 0)).is_err());
+    let mut broken = editor.clone();
+    broken.place(&Location::new(5, 5), &Player::Black).unwrap();
+    assert_eq!(broken.finish().unwrap_err(), ErrorKind::InvalidData);
+
+    let mut moves = HashSet::new();
+    moves.insert(Location::new(6, 6));
+    editor.set_legal_moves(&Player::Black, moves.clone());
+    let position = editor.clone().finish().unwrap();
+    assert_eq!(position.legal_black_moves, moves);
+    assert_eq!(position.turn, Player::Black);
+
+    moves.insert(Location::new(1, 0));
+    editor.set_legal_moves(&Player::Black, moves);
+    assert_eq!(editor.finish().unwrap_err(), ErrorKind::InvalidData);
+  }
+
+  #[test]
+  fn test_edit_keeps_crossing_links() {
+    let rules = RuleSet { blocking: Blocking::Crossing, capture: Capture::Never, ..RuleSet::default() };
+    let mut position = Position::new(Board::empty(5, 5), rules).unwrap();
+    for (x, y) in &[(1, 0), (2, 0), (2, 2), (0, 1)] {
+      position.make_move(Location::new(*x, *y)).unwrap();
+    }
+
+    // row by row, the white link (2,0) - (0,1) would come first and keep the black one from being made
+    let links = position.chain_links();
+    assert_eq!(links, vec![(Location::new(1, 0), Location::new(2, 2))]);
+    assert!(position.legal_black_moves.contains(&Location::new(3, 1)));
+
+    let edited = position.clone().edit().finish().unwrap();
+    assert_eq!(edited.chain_links(), links);
+    assert_eq!(edited.legal_black_moves, position.legal_black_moves);
+    assert_eq!(edited.legal_white_moves, position.legal_white_moves);
+    assert_eq!(edited.validate(), Ok(()));
+
+    // only the stones placed while editing are linked anew, after the ones already there
+    let mut editor = position.edit();
+    editor.place(&Location::new(0, 3), &Player::White).unwrap();
+    let edited = editor.finish().unwrap();
+    assert!(edited.chain_links().contains(&(Location::new(1, 0), Location::new(2, 2))));
+    assert_eq!(edited.group(&Location::new(0, 3)).unwrap().len(), 1);
+  }
+}
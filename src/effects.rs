@@ -0,0 +1,100 @@
+use std::collections::HashSet;
+
+use crate::position::Player;
+use crate::location::Location;
+
+/// The squares that became legal or illegal for one player.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LegalChanges {
+  pub added: HashSet<Location>,
+  pub removed: HashSet<Location>,
+}
+
+impl LegalChanges {
+  /// Returns the changes between the legal moves set before and after a move.
+  pub fn between(before: &HashSet<Location>, after: &HashSet<Location>) -> Self {
+    Self {
+      added: after.difference(before).cloned().collect(),
+      removed: before.difference(after).cloned().collect(),
+    }
+  }
+
+  /// Notes that a square became legal, which undoes an earlier removal of it.
+  pub(crate) fn add(&mut self, location: &Location) {
+    if !self.removed.remove(location) {
+      self.added.insert(location.to_owned());
+    }
+  }
+
+  /// Notes that a square became illegal, which undoes an earlier addition of it.
+  pub(crate) fn remove(&mut self, location: &Location) {
+    if !self.added.remove(location) {
+      self.removed.insert(location.to_owned());
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.added.is_empty() && self.removed.is_empty()
+  }
+}
+
+/// Everything a single move changes on the board, apart from the placed stone itself.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MoveEffects {
+  /// The captured stones, one set per chain that lost its anchor.
+  pub captured: Vec<HashSet<Location>>,
+  /// The links of the opponent that the placed stone blocks.
+  pub cut_links: Vec<(Location, Location)>,
+  pub black: LegalChanges,
+  pub white: LegalChanges,
+}
+
+impl MoveEffects {
+  /// Returns all captured stones, regardless of their chain.
+  pub fn captured_stones(&self) -> impl Iterator<Item = &Location> {
+    self.captured.iter().flatten()
+  }
+
+  pub fn legal_changes(&self, player: &Player) -> &LegalChanges {
+    match player {
+      Player::Black => &self.black,
+      Player::White => &self.white,
+    }
+  }
+}
+
+/// What happened when a move was played, as returned by `Position::make_move`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveOutcome {
+  /// The location the stone was placed on.
+  pub location: Location,
+  /// The player who placed the stone.
+  pub player: Player,
+  pub effects: MoveEffects,
+  /// The player whose turn it is after the move.
+  pub next_turn: Player,
+}
+
+impl MoveOutcome {
+  /// Returns all captured stones, regardless of their chain.
+  pub fn captured_stones(&self) -> impl Iterator<Item = &Location> {
+    self.effects.captured_stones()
+  }
+
+  pub fn legal_changes(&self, player: &Player) -> &LegalChanges {
+    self.effects.legal_changes(player)
+  }
+}
+
+impl std::fmt::Display for MoveOutcome {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{:?} played {}", self.player, self.location)?;
+
+    match (self.captured_stones().count(), self.effects.captured.len()) {
+      (0, _) => Ok(()),
+      (1, _) => write!(f, ", capturing 1 stone"),
+      (i, 1) => write!(f, ", capturing {} stones", i),
+      (i, j) => write!(f, ", capturing {} stones in {} chains", i, j),
+    }
+  }
+}
@@ -1,8 +1,203 @@
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
 
-#[allow(dead_code)]
-struct Engine<'e> {
-  evaluation_function: &'e Fn() -> f64,
+use crate::clock::{GameClock, TimeControl};
+use crate::location::Location;
+use crate::log::Level;
+use crate::network::Evaluator;
+use crate::position::{Player, Position};
+
+/// Roughly how many more moves a player is expected to make, the main time is split up over them.
+const MOVES_LEFT: u32 = 30;
+
+/// What a search found out about the moves of a position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Search {
+  /// The score of every legal move for the player to move, best first,
+  /// from -1 for a loss to 1 for a win.
+  pub scores: Vec<(Location, f32)>,
+  /// How many plies deep the scores look.
+  pub depth: u8,
+}
+
+impl Search {
+  pub fn best(&self) -> Option<&Location> {
+    self.scores.first().map(|i| &i.0)
+  }
+}
+
+/// An alpha-beta search that scores the positions at its horizon with an `Evaluator`.
+pub struct Engine<'e> {
+  evaluator: &'e dyn Evaluator,
   search_depth: u8,
 }
 
+impl<'e> Engine<'e> {
+  pub fn new(evaluator: &'e dyn Evaluator, search_depth: u8) -> Self {
+    log!(Level::Debug, "engine", "created engine searching {} plies deep", search_depth);
+
+    Self {
+      evaluator,
+      search_depth
+    }
+  }
+
+  /// Scores every legal move by searching one ply deeper at a time, up to `search_depth`
+  /// plies or until the time budget runs out, see `time_budget`. The first ply is always
+  /// searched in full and a deeper search that runs out of time is thrown away.
+  /// A player without a legal move has lost.
+  pub fn search(&self, position: &Position, budget: Option<Duration>) -> Result<Search, ErrorKind> {
+    let deadline = budget.map(|i| Instant::now() + i);
+    let mut result = Search {
+      scores: Vec::new(),
+      depth: 0,
+    };
+
+    for depth in 1..=self.search_depth.max(1) {
+      let scores = match self.score_moves(position, depth, deadline.filter(|_| depth > 1)) {
+        Ok(i) => i,
+        Err(ErrorKind::TimedOut) => break,
+        Err(e) => return Err(e),
+      };
+      log!(Level::Debug, "engine", "searched {} plies, best move {:?}", depth, scores.first());
+
+      result = Search {
+        scores,
+        depth
+      };
+      if deadline.is_some_and(|i| Instant::now() >= i) {
+        break;
+      }
+    }
+
+    Ok(result)
+  }
+
+  /// Returns the score of every legal move searched `depth` plies deep, best first.
+  fn score_moves(&self, position: &Position, depth: u8, deadline: Option<Instant>) -> Result<Vec<(Location, f32)>, ErrorKind> {
+    let mut scores = Vec::new();
+
+    for location in self.ordered_moves(position)? {
+      let child = play(position, &location)?;
+      scores.push((location, -self.negamax(&child, depth - 1, -1.0, 1.0, deadline)?));
+    }
+
+    // a stable sort, so moves that score the same stay in the order they were searched in
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+    Ok(scores)
+  }
+
+  /// Returns the score of a position for the player to move.
+  /// Fails with `TimedOut` once the deadline has passed.
+  fn negamax(&self, position: &Position, depth: u8, mut alpha: f32, beta: f32, deadline: Option<Instant>) -> Result<f32, ErrorKind> {
+    if deadline.is_some_and(|i| Instant::now() >= i) {
+      return Err(ErrorKind::TimedOut);
+    }
+    if position.legal_moves().is_empty() {
+      return Ok(-1.0);
+    }
+    if depth == 0 {
+      return Ok(self.evaluator.evaluate(position)?.value);
+    }
+
+    let mut best = -1.0f32;
+    for location in self.ordered_moves(position)? {
+      let child = play(position, &location)?;
+      let score = -self.negamax(&child, depth - 1, -beta, -alpha, deadline)?;
+
+      best = best.max(score);
+      alpha = alpha.max(score);
+      if alpha >= beta {
+        break;
+      }
+    }
+
+    Ok(best)
+  }
+
+  /// Returns the legal moves in the order they are searched in, the ones with the highest
+  /// prior first, so alpha-beta cuts off more of the others and ties keep that order.
+  fn ordered_moves(&self, position: &Position) -> Result<Vec<Location>, ErrorKind> {
+    let mut priors = self.evaluator.evaluate(position)?.priors;
+    // sorted by square first, the order of a set differs from run to run
+    priors.sort_by_key(|(i, _)| (i.x, i.y));
+    priors.sort_by(|a, b| b.1.total_cmp(&a.1));
+    Ok(priors.into_iter().map(|i| i.0).collect())
+  }
+}
+
+fn play(position: &Position, location: &Location) -> Result<Position, ErrorKind> {
+  let mut child = position.clone();
+  child.make_move(location.to_owned())?;
+  Ok(child)
+}
+
+/// Returns how long the engine should think about a move with the time a player has left:
+/// a share of the main time, plus most of an increment or of a byo-yomi period while there
+/// are periods left, or most of the time for the move under a fixed time per move.
+pub fn time_budget(clock: &GameClock, player: &Player) -> Duration {
+  let remaining = clock.remaining(player);
+  let share = remaining / MOVES_LEFT;
+
+  match clock.control() {
+    TimeControl::SuddenDeath { .. } => share,
+    TimeControl::Fischer { increment, .. } => (share + *increment * 4 / 5).min(remaining / 2),
+    TimeControl::ByoYomi { period, .. } if clock.periods(player) > 0 => share + *period * 4 / 5,
+    TimeControl::ByoYomi { .. } => share,
+    TimeControl::PerMove { .. } => remaining * 4 / 5,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
 
+  use crate::clock::GameClock;
+  use crate::engine::{self, Engine};
+  use crate::location::Location;
+  use crate::network::Network;
+  use crate::position::{Player, Position};
+
+  #[test]
+  fn test_search() {
+    // white is left without a move once black takes the last corner
+    let position = Position::from_diagram("B W B\nW . W\nB W .").unwrap();
+    let network = Network::new(3, 3, &[]);
+    let engine = Engine::new(&network, 3);
+
+    let search = engine.search(&position, None).unwrap();
+    assert_eq!(search.scores, vec![(Location::new(2, 2), 1.0)]);
+    assert_eq!(search.depth, 3);
+
+    // a search without any time left still looks one ply deep
+    let position = Position::from_diagram(". . .\n. . .\n. . .").unwrap();
+    let search = engine.search(&position, Some(Duration::ZERO)).unwrap();
+    assert_eq!(search.depth, 1);
+    assert_eq!(search.scores.len(), position.legal_moves().len());
+    assert!(search.scores.windows(2).all(|i| i[0].1 >= i[1].1));
+    assert_eq!(search.best(), Some(&search.scores[0].0));
+
+    // moves that score the same are in the order of their priors
+    let mut network = Network::new(3, 3, &[]);
+    network.policy.biases[8] = 1.0;
+    let search = Engine::new(&network, 1).search(&position, None).unwrap();
+    assert!(search.scores.iter().all(|i| i.1 == 0.0));
+    assert_eq!(search.best(), Some(&Location::new(2, 2)));
+
+    assert!(engine.search(&Position::default(), None).is_err());
+  }
+
+  #[test]
+  fn test_time_budget() {
+    let budget = |control: &str| engine::time_budget(&GameClock::new(control.parse().unwrap()), &Player::Black);
+    assert_eq!(budget("300"), Duration::from_secs(10));
+    assert_eq!(budget("300+5"), Duration::from_secs(14));
+    assert_eq!(budget("10/move"), Duration::from_secs(8));
+
+    // once the main time is used up, a period can be spent on every move
+    let mut clock = GameClock::new("60+2x10".parse().unwrap());
+    assert_eq!(engine::time_budget(&clock, &Player::White), Duration::from_secs(10));
+    clock.spend(&Player::White, Duration::from_secs(60)).unwrap();
+    assert_eq!(engine::time_budget(&clock, &Player::White), Duration::from_secs(8));
+  }
+}
@@ -0,0 +1,312 @@
+use std::collections::HashSet;
+
+use crate::board::BoardValue;
+use crate::location::Location;
+use crate::position::{Position, Player};
+
+/// The number of feature planes `encode` writes.
+pub const PLANES: usize = 10;
+
+/// Own stones, of the player to move.
+pub const OWN_STONES: usize = 0;
+/// Stones of the opponent.
+pub const OPPONENT_STONES: usize = 1;
+/// Squares the player to move may play on.
+pub const OWN_LEGAL: usize = 2;
+/// Squares the opponent may play on.
+pub const OPPONENT_LEGAL: usize = 3;
+/// All ones when black is to move, all zeros when white is.
+pub const BLACK_TO_MOVE: usize = 4;
+/// Distance to the nearest edge, 0 on the edge and 1 on the squares furthest from it.
+/// Wrapped sides are no edge, so on a torus it is 1 everywhere.
+pub const EDGE_DISTANCE: usize = 5;
+/// Own stones in groups that reach an anchor.
+pub const OWN_ANCHORED: usize = 6;
+/// Own stones in groups without an anchor.
+pub const OWN_UNANCHORED: usize = 7;
+/// Stones of the opponent in groups that reach an anchor.
+pub const OPPONENT_ANCHORED: usize = 8;
+/// Stones of the opponent in groups without an anchor.
+pub const OPPONENT_UNANCHORED: usize = 9;
+
+/// One of the eight ways to turn and mirror a board: the axes are swapped first,
+/// then the columns and the rows are reversed.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Symmetry {
+  pub transpose: bool,
+  pub flip_x: bool,
+  pub flip_y: bool,
+}
+
+impl Symmetry {
+  /// Returns the symmetries of a board of the given size, starting with the identity.
+  /// Only square boards can be transposed, so other boards have four.
+  pub fn all(size_x: usize, size_y: usize) -> Vec<Self> {
+    let mut result = Vec::new();
+    for transpose in [false, true] {
+      if transpose && size_x != size_y {
+        continue;
+      }
+      for flip_x in [false, true] {
+        for flip_y in [false, true] {
+          result.push(Self { transpose, flip_x, flip_y });
+        }
+      }
+    }
+    result
+  }
+
+  /// Moves a square of a board of the given size to where this symmetry puts it,
+  /// for example to move the target of a policy along with the planes.
+  pub fn apply(&self, location: &Location, size_x: usize, size_y: usize) -> Location {
+    let (mut x, mut y, size_x, size_y) = match self.transpose {
+      true => (location.y, location.x, size_y, size_x),
+      false => (location.x, location.y, size_x, size_y),
+    };
+
+    if self.flip_x {
+      x = size_x as isize - 1 - x;
+    }
+    if self.flip_y {
+      y = size_y as isize - 1 - y;
+    }
+
+    Location::new(x, y)
+  }
+
+  /// Turns an offset between two squares the way this symmetry turns the board.
+  fn apply_offset(&self, offset: &Location) -> Location {
+    let (x, y) = if self.transpose { (offset.y, offset.x) } else { (offset.x, offset.y) };
+    Location::new(if self.flip_x { -x } else { x }, if self.flip_y { -y } else { y })
+  }
+
+  /// Returns the symmetries that leave the rules of a position as they are, starting with
+  /// the identity: the holes, neutral stones and anchors of both players have to land on
+  /// squares of the same kind, the links on links of the same shape, and wrapped sides on
+  /// wrapped sides.
+  pub fn of_position(position: &Position) -> Vec<Self> {
+    let board = &position.board;
+    let rules = position.rules();
+    let (size_x, size_y) = (board.size_x, board.size_y);
+
+    let fixed = |location: &Location| match board.get_at(location) {
+      Ok(BoardValue::Blocked) => Some(BoardValue::Blocked),
+      Ok(BoardValue::Neutral) => Some(BoardValue::Neutral),
+      _ => None,
+    };
+    let blockers = |sets: &[Vec<Location>], symmetry: &Symmetry| -> HashSet<Vec<(isize, isize)>> {
+      sets
+        .iter()
+        .map(|set| {
+          let mut set: Vec<(isize, isize)> = set.iter().map(|i| symmetry.apply_offset(i)).map(|i| (i.x, i.y)).collect();
+          set.sort();
+          set
+        })
+        .collect()
+    };
+    let identity = Symmetry::default();
+
+    Self::all(size_x, size_y)
+      .into_iter()
+      .filter(|symmetry| !symmetry.transpose || board.topology.wraps_x() == board.topology.wraps_y())
+      .filter(|symmetry| {
+        rules.links.shapes().iter().all(|shape| match rules.links.shape(&symmetry.apply_offset(&shape.offset)) {
+          Some(i) => blockers(&i.blockers, &identity) == blockers(&shape.blockers, symmetry),
+          None => false,
+        })
+      })
+      .filter(|symmetry| {
+        (0..size_x).flat_map(|x| (0..size_y).map(move |y| Location::new(x as isize, y as isize))).all(|location| {
+          let target = symmetry.apply(&location, size_x, size_y);
+          fixed(&location) == fixed(&target) && [Player::Black, Player::White].iter().all(|player| {
+            rules.anchors.contains(board, &location, player) == rules.anchors.contains(board, &target, player)
+          })
+        })
+      })
+      .collect()
+  }
+}
+
+/// Encodes a position as `PLANES` planes of `size_x * size_y` values, seen from the
+/// player to move. See `encode_with` for the layout.
+pub fn encode(position: &Position) -> Vec<f32> {
+  encode_with(position, Symmetry::default())
+}
+
+/// Encodes a position turned or mirrored by a symmetry.
+///
+/// The planes follow each other in the order of their constants, each one row by row,
+/// so the value of square `(x, y)` on a plane is at `(plane * size_y + y) * size_x + x`.
+/// A transposing symmetry swaps `size_x` and `size_y` in that formula. Everything is
+/// 0 or 1 except `EDGE_DISTANCE`.
+///
+/// The planes do not say which squares are anchors or which sides wrap, so a symmetry
+/// should be one of `Symmetry::of_position`, as `augment` makes sure of.
+pub fn encode_with(position: &Position, symmetry: Symmetry) -> Vec<f32> {
+  let (size_x, size_y) = (position.board.size_x, position.board.size_y);
+  let area = size_x * size_y;
+  let own = position.turn.to_owned();
+  let opponent = own.switch();
+  let groups = position.groups();
+
+  // the distance of the squares furthest from any edge, wrapped axes have none
+  let (wraps_x, wraps_y) = (position.board.topology.wraps_x(), position.board.topology.wraps_y());
+  let shortest = match (wraps_x, wraps_y) {
+    (false, false) => size_x.min(size_y),
+    (true, false) => size_y,
+    (false, true) => size_x,
+    // no edges at all, every square is as far from one as can be
+    (true, true) => 0,
+  };
+  let furthest = (shortest.saturating_sub(1) / 2).max(1) as f32;
+  let black_to_move = if own == Player::Black { 1.0 } else { 0.0 };
+
+  let mut planes = vec![0.0; PLANES * area];
+
+  for y in 0..size_y {
+    for x in 0..size_x {
+      let location = Location::new(x as isize, y as isize);
+      let target = symmetry.apply(&location, size_x, size_y);
+      let target_x = if symmetry.transpose { size_y } else { size_x };
+      let index = target.y as usize * target_x + target.x as usize;
+      let mut set = |plane: usize, value: f32| planes[plane * area + index] = value;
+
+      if let Some(group) = groups.get(&location) {
+        let (stones, anchored) = match (group.player == own, group.anchored) {
+          (true, true) => (OWN_STONES, OWN_ANCHORED),
+          (true, false) => (OWN_STONES, OWN_UNANCHORED),
+          (false, true) => (OPPONENT_STONES, OPPONENT_ANCHORED),
+          (false, false) => (OPPONENT_STONES, OPPONENT_UNANCHORED),
+        };
+        set(stones, 1.0);
+        set(anchored, 1.0);
+      }
+
+      if position.legal_moves_for(&own).contains(&location) {
+        set(OWN_LEGAL, 1.0);
+      }
+      if position.legal_moves_for(&opponent).contains(&location) {
+        set(OPPONENT_LEGAL, 1.0);
+      }
+
+      set(BLACK_TO_MOVE, black_to_move);
+
+      let edge_x = if wraps_x { usize::MAX } else { x.min(size_x - 1 - x) };
+      let edge_y = if wraps_y { usize::MAX } else { y.min(size_y - 1 - y) };
+      set(EDGE_DISTANCE, (edge_x.min(edge_y) as f32 / furthest).min(1.0));
+    }
+  }
+
+  planes
+}
+
+/// Encodes a position once for every symmetry its rules allow, see `Symmetry::of_position`.
+pub fn augment(position: &Position) -> Vec<(Symmetry, Vec<f32>)> {
+  Symmetry::of_position(position)
+    .into_iter()
+    .map(|i| (i, encode_with(position, i)))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::board::{Board, BoardValue, Topology};
+  use crate::features::{self, Symmetry};
+  use crate::location::Location;
+  use crate::position::Position;
+  use crate::rules::{Anchors, Capture, RuleSet, Side};
+
+  #[test]
+  fn test_encode() {
+    let position = Position::from_diagram("
+      B . . . .
+      . . . . W
+      . . . . .
+      . . . . .
+    ").unwrap();
+    let planes = features::encode(&position);
+    let at = |planes: &[f32], plane: usize, x: usize, y: usize| planes[(plane * 4 + y) * 5 + x];
+
+    assert_eq!(planes.len(), features::PLANES * 20);
+    // black is to move
+    assert_eq!(at(&planes, features::OWN_STONES, 0, 0), 1.0);
+    assert_eq!(at(&planes, features::OPPONENT_STONES, 4, 1), 1.0);
+    assert_eq!(at(&planes, features::OWN_ANCHORED, 0, 0), 1.0);
+    assert_eq!(at(&planes, features::OPPONENT_ANCHORED, 4, 1), 1.0);
+    assert_eq!(at(&planes, features::OWN_LEGAL, 0, 0), 0.0);
+    assert_eq!(at(&planes, features::OWN_LEGAL, 4, 3), 1.0);
+    assert_eq!(at(&planes, features::BLACK_TO_MOVE, 3, 2), 1.0);
+    assert_eq!(at(&planes, features::EDGE_DISTANCE, 0, 2), 0.0);
+    assert_eq!(at(&planes, features::EDGE_DISTANCE, 2, 1), 1.0);
+
+    // only square boards can be transposed
+    assert_eq!(features::augment(&position).len(), 4);
+    let mirrored = features::encode_with(&position, Symmetry { flip_x: true, ..Symmetry::default() });
+    assert_eq!(at(&mirrored, features::OWN_STONES, 4, 0), 1.0);
+    assert_eq!(at(&mirrored, features::OPPONENT_STONES, 0, 1), 1.0);
+
+    // without captures a group can stay unanchored
+    let rules = RuleSet { capture: Capture::Never, ..RuleSet::default() };
+    let position = ". . .\n. B .\n. . W".parse::<crate::diagram::Diagram>().unwrap().position(rules).unwrap();
+    let planes = features::encode(&position);
+    assert_eq!(planes[(features::OWN_UNANCHORED * 3 + 1) * 3 + 1], 1.0);
+    assert_eq!(planes[(features::OPPONENT_ANCHORED * 3 + 2) * 3 + 2], 1.0);
+  }
+
+  #[test]
+  fn test_symmetries() {
+    let all = Symmetry::all(5, 5);
+    assert_eq!(all.len(), 8);
+
+    let location = Location::new(1, 0);
+    let mut moved: Vec<Location> = all.iter().map(|i| i.apply(&location, 5, 5)).collect();
+    moved.sort_by_key(|i| (i.x, i.y));
+    moved.dedup();
+    assert_eq!(moved.len(), 8);
+
+    let transposed = Symmetry { transpose: true, ..Symmetry::default() };
+    assert_eq!(transposed.apply(&Location::new(1, 0), 5, 5), Location::new(0, 1));
+  }
+
+  #[test]
+  fn test_symmetries_of_rules() {
+    let board = Board::empty(5, 5);
+    assert_eq!(Symmetry::of_position(&Position::new(board.clone(), RuleSet::default()).unwrap()).len(), 8);
+
+    // black anchors on top and bottom, white on the sides: turning the board swaps them
+    let rules = RuleSet {
+      anchors: Anchors::Sides { black: vec![Side::Top, Side::Bottom], white: vec![Side::Left, Side::Right] },
+      ..RuleSet::default()
+    };
+    let position = Position::new(board.clone(), rules).unwrap();
+    assert!(Symmetry::of_position(&position).iter().all(|i| !i.transpose));
+    assert_eq!(features::augment(&position).len(), 4);
+
+    // an anchor row only maps onto itself without flipping the rows, and the cylinder
+    // can't be turned, its wrapped side would end up at the top
+    let rules = RuleSet { anchors: Anchors::Rows(vec![0]), ..RuleSet::default() };
+    let position = Position::new(board.clone().with_topology(Topology::Cylinder), rules).unwrap();
+    let flip_x = Symmetry { flip_x: true, ..Symmetry::default() };
+    assert_eq!(Symmetry::of_position(&position), vec![Symmetry::default(), flip_x]);
+
+    // a hole in a corner keeps only the symmetries that leave that corner where it is
+    let mut holed = board.clone();
+    holed.set_at(&Location::new(0, 0), BoardValue::Blocked).unwrap();
+    let transposed = Symmetry { transpose: true, ..Symmetry::default() };
+    assert_eq!(Symmetry::of_position(&Position::new(holed, RuleSet::default()).unwrap()), vec![Symmetry::default(), transposed]);
+  }
+
+  #[test]
+  fn test_edge_distance_on_wrapped_boards() {
+    let at = |planes: &[f32], x: usize, y: usize| planes[(features::EDGE_DISTANCE * 5 + y) * 5 + x];
+    let rules = RuleSet { anchors: Anchors::Rows(vec![0]), ..RuleSet::default() };
+
+    // the left and right side are joined, so only the rows are near an edge
+    let cylinder = features::encode(&Position::new(Board::empty(5, 5).with_topology(Topology::Cylinder), rules.clone()).unwrap());
+    assert_eq!(at(&cylinder, 0, 2), 1.0);
+    assert_eq!(at(&cylinder, 2, 0), 0.0);
+
+    let torus = features::encode(&Position::new(Board::empty(5, 5).with_topology(Topology::Torus), rules).unwrap());
+    assert!((0..5).all(|x| (0..5).all(|y| at(&torus, x, y) == 1.0)));
+  }
+}
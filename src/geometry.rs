@@ -0,0 +1,189 @@
+use crate::location::Location;
+
+/// A link from a stone to the square at `offset`, relative to that stone.
+///
+/// The link is interrupted when the opponent has a stone in every one of the blocker sets.
+/// For the knight move to the right and down, with the stone at `W`:
+///
+/// ```text
+/// W C D
+/// B A L
+/// ```
+///
+/// the link to `L` is blocked when `(A | B) & (C | D)` are taken by the opponent,
+/// which is `offset: (2, 1)` with the blockers `[(0, 1), (1, 1)]` and `[(1, 0), (2, 0)]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkShape {
+  pub offset: Location,
+  pub blockers: Vec<Vec<Location>>,
+}
+
+impl LinkShape {
+  pub fn new(offset: (isize, isize), blockers: &[&[(isize, isize)]]) -> Self {
+    Self {
+      offset: Location::new(offset.0, offset.1),
+      blockers: blockers
+        .iter()
+        .map(|set| set.iter().map(|(x, y)| Location::new(*x, *y)).collect())
+        .collect(),
+    }
+  }
+
+  /// Returns this shape with every offset passed through the same function.
+  fn map(&self, f: impl Fn(&Location) -> Location) -> Self {
+    Self {
+      offset: f(&self.offset),
+      blockers: self.blockers.iter().map(|set| set.iter().map(&f).collect()).collect(),
+    }
+  }
+}
+
+/// All the ways two stones can be linked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Geometry {
+  shapes: Vec<LinkShape>,
+}
+
+impl Geometry {
+  /// Creates a geometry out of exactly the given shapes.
+  /// Links are undirected, so every shape should have a mirrored counterpart.
+  pub fn new(shapes: Vec<LinkShape>) -> Self {
+    Self {
+      shapes
+    }
+  }
+
+  /// Creates a geometry out of a shape and all its rotations and reflections.
+  pub fn symmetric(shape: LinkShape) -> Self {
+    let symmetries: [fn(&Location) -> Location; 8] = [
+      |i| Location::new(i.x, i.y),
+      |i| Location::new(-i.x, i.y),
+      |i| Location::new(i.x, -i.y),
+      |i| Location::new(-i.x, -i.y),
+      |i| Location::new(i.y, i.x),
+      |i| Location::new(-i.y, i.x),
+      |i| Location::new(i.y, -i.x),
+      |i| Location::new(-i.y, -i.x),
+    ];
+
+    let mut shapes: Vec<LinkShape> = Vec::new();
+    for symmetry in &symmetries {
+      let shape = shape.map(symmetry);
+      if shapes.iter().all(|i| i.offset != shape.offset) {
+        shapes.push(shape);
+      }
+    }
+
+    Self::new(shapes)
+  }
+
+  /// The knight move links of the standard game.
+  pub fn knight() -> Self {
+    Self::symmetric(LinkShape::new((2, 1), &[&[(0, 1), (1, 1)], &[(1, 0), (2, 0)]]))
+  }
+
+  /// Returns how far a link reaches along either axis.
+  pub fn reach(&self) -> isize {
+    self.offsets().map(|i| i.x.abs().max(i.y.abs())).max().unwrap_or(0)
+  }
+
+  pub fn shapes(&self) -> &[LinkShape] {
+    &self.shapes
+  }
+
+  /// Returns the offsets from a stone to every square it can link to.
+  pub fn offsets(&self) -> impl Iterator<Item = &Location> {
+    self.shapes.iter().map(|i| &i.offset)
+  }
+
+  /// Returns the shape of the link to the square at an offset, if there is one.
+  pub fn shape(&self, offset: &Location) -> Option<&LinkShape> {
+    self.shapes.iter().find(|i| i.offset == *offset)
+  }
+
+  /// Returns the ends of every link that a stone could help block, relative to that stone.
+  pub fn passing(&self) -> Vec<(Location, Location)> {
+    let mut result = Vec::new();
+
+    for shape in &self.shapes {
+      for blocker in shape.blockers.iter().flatten() {
+        let from = Location::new(-blocker.x, -blocker.y);
+        let to = &from + &shape.offset;
+        if !result.contains(&(from.clone(), to.clone())) {
+          result.push((from, to));
+        }
+      }
+    }
+
+    result
+  }
+}
+
+/// Returns whether the links `a - b` and `c - d` cross each other.
+/// Links that only share an end, or lie on the same line, do not cross.
+pub fn crosses(a: &Location, b: &Location, c: &Location, d: &Location) -> bool {
+  // on which side of the line through `from` and `to` a point lies
+  fn side(from: &Location, to: &Location, point: &Location) -> isize {
+    ((to.x - from.x) * (point.y - from.y) - (to.y - from.y) * (point.x - from.x)).signum()
+  }
+
+  side(a, b, c) * side(a, b, d) < 0 && side(c, d, a) * side(c, d, b) < 0
+}
+
+impl Default for Geometry {
+  fn default() -> Self {
+    Self::knight()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashSet;
+
+  use crate::geometry::{self, Geometry};
+  use crate::location::Location;
+
+  fn set(offsets: &[(isize, isize)]) -> HashSet<Location> {
+    offsets.iter().map(|(x, y)| Location::new(*x, *y)).collect()
+  }
+
+  #[test]
+  fn test_knight() {
+    let knight = Geometry::knight();
+
+    assert_eq!(knight.shapes().len(), 8);
+    assert_eq!(
+      knight.offsets().cloned().collect::<HashSet<_>>(),
+      set(&[(2, 1), (2, -1), (-2, 1), (-2, -1), (1, 2), (1, -2), (-1, 2), (-1, -2)])
+    );
+
+    // every link goes both ways, and is blocked by the same squares from either end
+    for shape in knight.shapes() {
+      let back = knight.shape(&Location::new(-shape.offset.x, -shape.offset.y)).unwrap();
+      let blockers: HashSet<_> = shape.blockers.iter().flatten().cloned().collect();
+      let back_blockers: HashSet<_> = back.blockers.iter().flatten().map(|i| i + &shape.offset).collect();
+      assert_eq!(blockers, back_blockers);
+    }
+
+    let shape = knight.shape(&Location::new(-1, -2)).unwrap();
+    assert_eq!(set(&[(-1, 0), (-1, -1)]), shape.blockers[0].iter().cloned().collect());
+    assert_eq!(set(&[(0, -1), (0, -2)]), shape.blockers[1].iter().cloned().collect());
+
+    assert!(knight.shape(&Location::new(1, 1)).is_none());
+    assert_eq!(knight.passing().len(), 32);
+    assert_eq!(knight.reach(), 2);
+  }
+
+  #[test]
+  fn test_crosses() {
+    let l = |x, y| Location::new(x, y);
+
+    assert!(geometry::crosses(&l(0, 0), &l(2, 1), &l(1, 0), &l(0, 2)));
+    assert!(geometry::crosses(&l(1, 0), &l(2, 2), &l(0, 0), &l(2, 1)));
+    // sharing an end
+    assert!(!geometry::crosses(&l(0, 0), &l(2, 1), &l(2, 1), &l(0, 2)));
+    // parallel, and on the same line
+    assert!(!geometry::crosses(&l(0, 0), &l(2, 1), &l(0, 1), &l(2, 2)));
+    assert!(!geometry::crosses(&l(0, 0), &l(2, 1), &l(2, 1), &l(4, 2)));
+  }
+}
@@ -0,0 +1,179 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::position::Player;
+use crate::location::Location;
+
+/// A disjoint-set forest over the numbers `0..len`,
+/// with union by rank and path compression.
+#[derive(Debug, Clone)]
+pub struct UnionFind {
+  parent: Vec<usize>,
+  rank: Vec<u8>,
+}
+
+impl UnionFind {
+  pub fn new(len: usize) -> Self {
+    Self {
+      parent: (0..len).collect(),
+      rank: vec![0; len],
+    }
+  }
+
+  /// Returns the representative of the set containing `i`.
+  pub fn find(&mut self, i: usize) -> usize {
+    let mut root = i;
+    while self.parent[root] != root {
+      root = self.parent[root];
+    }
+
+    let mut current = i;
+    while self.parent[current] != root {
+      let next = self.parent[current];
+      self.parent[current] = root;
+      current = next;
+    }
+
+    root
+  }
+
+  /// Merges the sets containing `a` and `b`.
+  /// Returns false when they already were the same set.
+  pub fn union(&mut self, a: usize, b: usize) -> bool {
+    let (a, b) = (self.find(a), self.find(b));
+    if a == b {
+      return false;
+    }
+
+    if self.rank[a] < self.rank[b] {
+      self.parent[a] = b;
+    } else {
+      self.parent[b] = a;
+      if self.rank[a] == self.rank[b] {
+        self.rank[a] += 1;
+      }
+    }
+
+    true
+  }
+}
+
+/// Stones of one player that are all linked to each other, directly or through other stones.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+  pub player: Player,
+  pub stones: HashSet<Location>,
+  /// Whether any of the stones is on an anchor square of the player.
+  pub anchored: bool,
+  /// The empty squares any of the stones can link to.
+  pub liberties: HashSet<Location>,
+}
+
+impl Group {
+  pub fn size(&self) -> usize {
+    self.stones.len()
+  }
+
+  pub fn contains(&self, location: &Location) -> bool {
+    self.stones.contains(location)
+  }
+}
+
+/// A partition of all stones on the board into groups.
+#[derive(Debug, Clone, Default)]
+pub struct Groups {
+  groups: Vec<Group>,
+  index: HashMap<Location, usize>,
+}
+
+impl Groups {
+  pub(crate) fn new(groups: Vec<Group>) -> Self {
+    let index = groups
+      .iter()
+      .enumerate()
+      .flat_map(|(i, group)| group.stones.iter().map(move |stone| (stone.to_owned(), i)))
+      .collect();
+
+    Self {
+      groups,
+      index
+    }
+  }
+
+  /// Returns the group containing the stone at a location, if there is one.
+  pub fn get(&self, location: &Location) -> Option<&Group> {
+    self.index.get(location).map(|i| &self.groups[*i])
+  }
+
+  pub fn iter(&self) -> std::slice::Iter<'_, Group> {
+    self.groups.iter()
+  }
+
+  /// Returns all groups of one player.
+  pub fn of_player<'g>(&'g self, player: &'g Player) -> impl Iterator<Item = &'g Group> + 'g {
+    self.groups.iter().filter(move |i| i.player == *player)
+  }
+
+  pub fn len(&self) -> usize {
+    self.groups.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.groups.is_empty()
+  }
+}
+
+impl<'g> IntoIterator for &'g Groups {
+  type Item = &'g Group;
+  type IntoIter = std::slice::Iter<'g, Group>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::group::UnionFind;
+  use crate::position::{Position, Player};
+  use crate::location::Location;
+
+  #[test]
+  fn test_union_find() {
+    let mut sets = UnionFind::new(5);
+
+    assert!(sets.union(0, 1));
+    assert!(sets.union(3, 4));
+    assert!(!sets.union(1, 0));
+    assert!(sets.union(1, 4));
+
+    assert_eq!(sets.find(0), sets.find(3));
+    assert_ne!(sets.find(0), sets.find(2));
+  }
+
+  #[test]
+  fn test_groups() {
+    let mut position = Position::default();
+    position.make_move(Location::new(0, 0)).unwrap();
+    position.make_move(Location::new(12, 12)).unwrap();
+    position.make_move(Location::new(1, 2)).unwrap();
+    position.make_move(Location::new(0, 12)).unwrap();
+    position.make_move(Location::new(3, 3)).unwrap();
+
+    let groups = position.groups();
+    assert_eq!(groups.len(), 3);
+    assert_eq!(groups.of_player(&Player::Black).count(), 1);
+
+    let black = groups.get(&Location::new(3, 3)).unwrap();
+    assert_eq!(black.size(), 3);
+    assert!(black.contains(&Location::new(0, 0)));
+    assert!(black.anchored);
+    assert!(black.liberties.contains(&Location::new(5, 4)));
+    assert!(!black.liberties.contains(&Location::new(1, 2)));
+
+    let white = groups.get(&Location::new(12, 12)).unwrap();
+    assert_eq!(white.size(), 1);
+    assert_eq!(white.liberties.len(), 2);
+
+    assert!(groups.get(&Location::new(6, 6)).is_none());
+  }
+}
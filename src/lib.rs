@@ -0,0 +1,39 @@
+//! Connecticut, a connection game played with knight-move links on a square board.
+//!
+//! `Position` holds the board, the side to move and the legal squares for both players,
+//! and is the entry point for playing, rendering and analysing games.
+
+#[macro_use]
+pub mod log;
+
+pub mod position;
+pub mod location;
+pub mod board;
+pub mod engine;
+pub mod render;
+pub mod svg;
+pub mod record;
+pub mod group;
+pub mod chain;
+pub mod effects;
+pub mod rules;
+pub mod geometry;
+pub mod editor;
+pub mod diagram;
+pub mod violation;
+pub mod stress;
+pub mod clock;
+pub mod selfplay;
+pub mod features;
+pub mod network;
+
+pub use crate::position::{Position, Player};
+pub use crate::board::{Board, BoardValue};
+pub use crate::location::Location;
+pub use crate::engine::Engine;
+pub use crate::group::{Group, Groups};
+pub use crate::effects::{MoveEffects, MoveOutcome};
+pub use crate::rules::RuleSet;
+pub use crate::editor::Editor;
+pub use crate::clock::{GameClock, TimeControl};
+pub use crate::network::{Evaluation, Evaluator, Network, Uniform};
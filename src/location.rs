@@ -16,13 +16,20 @@ impl Location {
   // }
 }
 
+/// Shows a location the way moves are typed in, as `x y`.
+impl std::fmt::Display for Location {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{} {}", self.x, self.y)
+  }
+}
+
 impl Add<&Location> for &Location{
   type Output = Location;
 
   fn add (self, location: &Location) -> Self::Output {
     Self::Output {
-      x: location.x as isize + self.x,
-      y: location.y as isize + self.y
+      x: location.x + self.x,
+      y: location.y + self.y
     }
   }
 }
@@ -0,0 +1,213 @@
+//! A small levelled logger, silent unless switched on.
+//!
+//! Every line carries a level and a target, the part of the program it comes from
+//! (`position`, `engine` or `cli`), followed by the message:
+//!
+//! ```text
+//! [TRACE position] cut link 0 0 - 2 1
+//! ```
+//!
+//! What gets written is configured with a filter like `debug` or `info,position=trace`,
+//! where a bare level applies to every target not mentioned by name.
+
+use std::io::{ErrorKind, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+  Off,
+  Error,
+  Warn,
+  Info,
+  Debug,
+  Trace,
+}
+
+impl Level {
+  fn name(self) -> &'static str {
+    match self {
+      Level::Off => "OFF",
+      Level::Error => "ERROR",
+      Level::Warn => "WARN",
+      Level::Info => "INFO",
+      Level::Debug => "DEBUG",
+      Level::Trace => "TRACE",
+    }
+  }
+}
+
+impl FromStr for Level {
+  type Err = ErrorKind;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "off" => Ok(Level::Off),
+      "error" => Ok(Level::Error),
+      "warn" => Ok(Level::Warn),
+      "info" => Ok(Level::Info),
+      "debug" => Ok(Level::Debug),
+      "trace" => Ok(Level::Trace),
+      _ => Err(ErrorKind::InvalidInput),
+    }
+  }
+}
+
+/// Which levels are written for which targets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+  default: Level,
+  targets: Vec<(String, Level)>,
+}
+
+impl Filter {
+  pub fn level_for(&self, target: &str) -> Level {
+    self.targets
+      .iter()
+      .find(|(i, _)| i == target)
+      .map_or(self.default, |(_, level)| *level)
+  }
+
+  /// The most verbose level of any target.
+  fn max_level(&self) -> Level {
+    self.targets.iter().map(|(_, i)| *i).fold(self.default, Level::max)
+  }
+}
+
+impl FromStr for Filter {
+  type Err = ErrorKind;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut filter = Filter {
+      default: Level::Off,
+      targets: Vec::new(),
+    };
+
+    for part in s.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+      match part.find('=') {
+        Some(i) => filter.targets.push((part[..i].to_owned(), part[i + 1..].parse()?)),
+        None => filter.default = part.parse()?,
+      }
+    }
+
+    Ok(filter)
+  }
+}
+
+/// The most verbose level any target has, so most calls can be rejected without locking.
+static MAX_LEVEL: AtomicUsize = AtomicUsize::new(Level::Off as usize);
+static FILTER: RwLock<Option<Filter>> = RwLock::new(None);
+static SINK: Mutex<Option<Box<dyn Write + Send>>> = Mutex::new(None);
+
+/// Replaces the current filter.
+pub fn set_filter(filter: Filter) {
+  MAX_LEVEL.store(filter.max_level() as usize, Ordering::Relaxed);
+  *FILTER.write().unwrap_or_else(|i| i.into_inner()) = Some(filter);
+}
+
+/// Writes everything up to a level, for all targets.
+pub fn set_level(level: Level) {
+  set_filter(Filter {
+    default: level,
+    targets: Vec::new(),
+  });
+}
+
+/// Parses a filter such as `info,position=trace` and makes it the current one.
+pub fn configure(spec: &str) -> Result<(), ErrorKind> {
+  set_filter(spec.parse()?);
+  Ok(())
+}
+
+/// Sends log lines somewhere else than stderr, for example a file or a buffer in a test.
+pub fn set_sink(sink: Box<dyn Write + Send>) {
+  *SINK.lock().unwrap_or_else(|i| i.into_inner()) = Some(sink);
+}
+
+pub fn enabled(level: Level, target: &str) -> bool {
+  if level == Level::Off || level as usize > MAX_LEVEL.load(Ordering::Relaxed) {
+    return false;
+  }
+
+  match &*FILTER.read().unwrap_or_else(|i| i.into_inner()) {
+    Some(filter) => level <= filter.level_for(target),
+    None => false,
+  }
+}
+
+/// Writes a single line, use the `log!` macro instead so the message
+/// is only formatted when it will actually be written.
+pub fn write(level: Level, target: &str, message: std::fmt::Arguments<'_>) {
+  let line = format!("[{} {}] {}\n", level.name(), target, message);
+  let mut sink = SINK.lock().unwrap_or_else(|i| i.into_inner());
+
+  // logging should never bring the program down, so failed writes are dropped
+  let _ = match sink.as_mut() {
+    Some(i) => i.write_all(line.as_bytes()),
+    None => std::io::stderr().write_all(line.as_bytes()),
+  };
+}
+
+/// Logs a message for a target at a level, if the current filter lets it through:
+/// `log!(Level::Trace, "position", "captured {} stones", count)`.
+#[macro_export]
+macro_rules! log {
+  ($level:expr, $target:expr, $($arg:tt)+) => {
+    if $crate::log::enabled($level, $target) {
+      $crate::log::write($level, $target, format_args!($($arg)+));
+    }
+  };
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::Write;
+  use std::sync::{Arc, Mutex};
+
+  use crate::log::{self, Filter, Level};
+
+  /// A sink the test can read back after handing it to the logger.
+  #[derive(Clone, Default)]
+  struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+  impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+      self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn test_filter() {
+    let filter: Filter = "info, position=trace,engine=off".parse().unwrap();
+
+    assert_eq!(filter.level_for("position"), Level::Trace);
+    assert_eq!(filter.level_for("engine"), Level::Off);
+    assert_eq!(filter.level_for("cli"), Level::Info);
+    assert_eq!(filter.max_level(), Level::Trace);
+
+    assert_eq!("".parse::<Filter>().unwrap().level_for("cli"), Level::Off);
+    assert!("position=loud".parse::<Filter>().is_err());
+  }
+
+  #[test]
+  fn test_log_to_sink() {
+    // the logger is global, so only a target no other test logs to is switched on
+    let buffer = Buffer::default();
+    log::set_sink(Box::new(buffer.clone()));
+    log::configure("off,log-test=info").unwrap();
+
+    assert!(!log::enabled(Level::Debug, "log-test"));
+    assert!(!log::enabled(Level::Error, "position"));
+    log!(Level::Debug, "log-test", "too verbose");
+    log!(Level::Error, "other-test", "not switched on");
+    log!(Level::Info, "log-test", "{} stones", 3);
+
+    let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    assert_eq!(output, "[INFO log-test] 3 stones\n");
+  }
+}
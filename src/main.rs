@@ -1,59 +1,447 @@
-#[cfg(feature = "with_cargo")]
-mod position;
-#[cfg(feature = "with_cargo")]
-mod board;
-#[cfg(feature = "with_cargo")]
-mod location;
-#[cfg(feature = "with_cargo")]
-mod engine;
-
-#[cfg(feature = "with_cargo")]
-use position::Position;
-#[cfg(feature = "with_cargo")]
-use location::Location;
-
-#[cfg(not(feature = "with_cargo"))]
-use src::position::Position;
-
-#[cfg(not(feature = "with_cargo"))]
-use src::location::Location;
+use connecticut::{log, Board, Engine, GameClock, Location, Network, Player, Position, RuleSet, Uniform};
+use connecticut::board::Topology;
+use connecticut::rules::{Blocking, Capture};
+use connecticut::log::Level;
+use connecticut::record::GameRecord;
+use connecticut::render::RenderMode;
+use connecticut::{selfplay, stress};
+use connecticut::svg::{self, AnimatedSvg, SvgOptions};
 
 use std::io;
+use std::time::{Duration, Instant};
+
+/// Reads the render mode from the command line (`--render <mode>` or one of the
+/// `--ascii`, `--unicode` and `--color` shorthands), falling back to auto-detection.
+fn render_mode() -> RenderMode {
+  let mut mode = RenderMode::detect();
+  let mut args = std::env::args().skip(1);
+
+  while let Some(arg) = args.next() {
+    let name = match arg.as_str() {
+      "--render" => args.next().unwrap_or_default(),
+      "--ascii" | "--plain" => "ascii".to_owned(),
+      "--unicode" => "unicode".to_owned(),
+      "--color" | "--colour" => "ansi".to_owned(),
+      "--log" => {
+        args.next();
+        continue;
+      },
+      other => match other.strip_prefix("--render=") {
+        Some(name) => name.to_owned(),
+        None => continue,
+      }
+    };
+
+    match RenderMode::from_name(&name) {
+      Some(i) => mode = i,
+      None => eprintln!("Unknown render mode {:?}, expected ansi, ascii, unicode or auto", name),
+    }
+  }
+
+  mode
+}
+
+/// Writes the game so far either as one animated SVG,
+/// or as numbered frames `<path>-000.svg`, `<path>-001.svg`, ...
+fn export_game(record: &GameRecord, path: &str, animated: bool) -> Result<(), String> {
+  let positions = record.positions().map_err(|e| format!("{:?}", e))?;
+
+  if animated {
+    let animation = AnimatedSvg::new(&positions, SvgOptions::default(), 1.0).map_err(|e| format!("{:?}", e))?;
+    std::fs::write(path, animation.to_string()).map_err(|e| e.to_string())
+  } else {
+    for (i, frame) in svg::frames(&positions, SvgOptions::default()).iter().enumerate() {
+      std::fs::write(format!("{}-{:03}.svg", path, i), frame).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+  }
+}
+
+/// Prints what playing at a location would do, without playing it.
+fn preview(position: &Position, location: &Location) {
+  let effects = match position.simulate_move(location) {
+    Ok(i) => i,
+    Err(_) => return println!("Couldn't put a piece there!"),
+  };
+
+  println!("Playing {} would:", location);
+  for (from, to) in &effects.cut_links {
+    println!("  cut the link {} - {}", from, to);
+  }
+  for chain in &effects.captured {
+    let stones: Vec<String> = chain.iter().map(Location::to_string).collect();
+    println!("  capture {}", stones.join(", "));
+  }
+  for player in &[Player::Black, Player::White] {
+    let changes = effects.legal_changes(player);
+    println!(
+      "  give {:?} {} new and take away {} legal squares",
+      player, changes.added.len(), changes.removed.len()
+    );
+  }
+}
+
+/// Switches on logging from the `CONNECTICUT_LOG` environment variable
+/// or the `--log <filter>` argument, for example `--log info,position=trace`.
+fn configure_logging() {
+  let mut spec = std::env::var("CONNECTICUT_LOG").ok();
+  let mut args = std::env::args().skip(1);
+
+  while let Some(arg) = args.next() {
+    if arg == "--log" {
+      spec = args.next();
+    } else if let Some(i) = arg.strip_prefix("--log=") {
+      spec = Some(i.to_owned());
+    }
+  }
+
+  if let Some(spec) = spec {
+    if connecticut::log::configure(&spec).is_err() {
+      eprintln!("Invalid log filter {:?}, expected something like info,position=trace", spec);
+    }
+  }
+}
+
+/// Plays random games and checks every move against a recomputation from scratch,
+/// `stress [games] [seed]`. A divergence is printed as a game that can be piped back in.
+/// The board and rules are picked with `--size <x> <y>`, `--topology <flat|cylinder|torus>`,
+/// `--blocking <occupancy|crossing|never>` and `--capture <unanchored|never>`.
+fn stress() {
+  let usage = || {
+    println!("Usage: stress [games] [seed] [--size <x> <y>] [--topology <name>] [--blocking <name>] [--capture <name>]");
+    std::process::exit(1);
+  };
+
+  let mut board = Board::default();
+  let mut rules = RuleSet::default();
+  let mut numbers = Vec::new();
+  let mut args = std::env::args().skip(2);
+
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--size" => match (args.next().map(|i| i.parse()), args.next().map(|i| i.parse())) {
+        (Some(Ok(x)), Some(Ok(y))) => board = Board::empty(x, y).with_topology(board.topology),
+        _ => usage(),
+      },
+      "--topology" => match args.next().as_deref().and_then(Topology::from_name) {
+        Some(i) => board = board.with_topology(i),
+        None => usage(),
+      },
+      "--blocking" => match args.next().as_deref().and_then(Blocking::from_name) {
+        Some(i) => rules.blocking = i,
+        None => usage(),
+      },
+      "--capture" => match args.next().as_deref().and_then(Capture::from_name) {
+        Some(i) => rules.capture = i,
+        None => usage(),
+      },
+      "--log" => {
+        args.next();
+      },
+      other => match other.parse::<u64>() {
+        Ok(i) => numbers.push(i),
+        Err(_) if other.starts_with("--") => (),
+        Err(_) => usage(),
+      },
+    }
+  }
+  let games = numbers.first().cloned().unwrap_or(1000) as usize;
+  let seed = numbers.get(1).cloned().unwrap_or(1);
+  let start = match Position::new(board, rules) {
+    Ok(i) => i,
+    Err(_) => {
+      println!("These rules can't be played on this board");
+      std::process::exit(1);
+    },
+  };
+
+  // a failed check in a debug build panics, which is reported as a divergence instead
+  std::panic::set_hook(Box::new(|_| ()));
+
+  println!("Playing {} random games with seed {}", games, seed);
+  match stress::run(&start, games, 200, seed) {
+    Ok(moves) => println!("Checked {} moves, no divergences", moves),
+    Err(divergence) => {
+      println!("Diverged, shrunk to {} moves:", divergence.moves.len());
+      print!("{}", divergence);
+      std::process::exit(1);
+    },
+  }
+}
+
+/// Writes training samples from self-play games as JSON lines,
+/// `selfplay <file> [games] [seed] [--network <file>] [--depth <plies>]`. Without a
+/// network the search only sees wins and losses. See `selfplay::Sample` for the format.
+fn selfplay() {
+  let usage = || {
+    println!("Usage: selfplay <file> [games] [seed] [--network <file>] [--depth <plies>]");
+    std::process::exit(1);
+  };
+
+  let mut path = None;
+  let mut network = None;
+  let mut depth = 1;
+  let mut numbers = Vec::new();
+  let mut args = std::env::args().skip(2);
+
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--network" => match args.next().map(|i| Network::load(&i).map_err(|e| (i, e))) {
+        Some(Ok(i)) => network = Some(i),
+        Some(Err((file, e))) => {
+          println!("Couldn't read the network {}: {:?}", file, e);
+          std::process::exit(1);
+        },
+        None => usage(),
+      },
+      "--depth" => match args.next().map(|i| i.parse()) {
+        Some(Ok(i)) => depth = i,
+        _ => usage(),
+      },
+      "--log" => {
+        args.next();
+      },
+      other if other.starts_with("--") => (),
+      other if path.is_none() => path = Some(other.to_owned()),
+      other => match other.parse::<u64>() {
+        Ok(i) => numbers.push(i),
+        Err(_) => usage(),
+      },
+    }
+  }
+  let path = match path {
+    Some(i) => i,
+    None => return usage(),
+  };
+  let games = numbers.first().cloned().unwrap_or(100) as usize;
+  let seed = numbers.get(1).cloned().unwrap_or(1);
+  let engine = match &network {
+    Some(i) => Engine::new(i, depth),
+    None => Engine::new(&Uniform, depth),
+  };
+
+  let result = std::fs::File::create(&path)
+    .map_err(|e| e.kind())
+    .and_then(|file| selfplay::run(&Position::default(), &engine, games, 200, seed, &mut io::BufWriter::new(file)));
+
+  match result {
+    Ok(samples) => println!("Wrote {} samples from {} games to {}", samples, games, path),
+    Err(e) => {
+      println!("Couldn't write {}: {:?}", path, e);
+      std::process::exit(1);
+    },
+  }
+}
+
+/// Reads how the game starts from the command line: `--swap` lets white take over
+/// the first move, every `--handicap <x> <y>` puts a black stone on the board
+/// and `--clock <control>` times the game, for example `--clock 300+5`.
+fn start() -> GameRecord {
+  let mut record = GameRecord::new();
+  let mut args = std::env::args().skip(1);
+
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--swap" => record.rules.swap = true,
+      "--handicap" => match (args.next().map(|i| i.parse()), args.next().map(|i| i.parse())) {
+        (Some(Ok(x)), Some(Ok(y))) => record.handicap.push(Location::new(x, y)),
+        _ => eprintln!("Usage: --handicap <x> <y>"),
+      },
+      "--clock" => match args.next().map(|i| i.parse()) {
+        Some(Ok(i)) => record.clock = Some(i),
+        _ => eprintln!("Usage: --clock <seconds>, <seconds>+<increment>, <seconds>+<periods>x<seconds> or <seconds>/move"),
+      },
+      _ => (),
+    }
+  }
+
+  record
+}
+
+/// Clears the terminal, but only when we are allowed to write escape codes.
+fn clear_screen(mode: RenderMode) {
+  if mode.uses_color() {
+    println!("{}[2J", 27 as char);
+  }
+}
 
 pub fn main() {
 
-  let mut position = Position::default();
-  println!("{}[2J", 27 as char);
-  
+  configure_logging();
+  if std::env::args().nth(1).as_deref() == Some("stress") {
+    return stress();
+  }
+  if std::env::args().nth(1).as_deref() == Some("selfplay") {
+    return selfplay();
+  }
+
+  let mode = render_mode();
+  log!(Level::Info, "cli", "rendering in {:?} mode", mode);
+  let mut show_links = false;
+  let mut record = start();
+
+  let mut position = match record.start() {
+    Ok(i) => i,
+    Err(e) => {
+      eprintln!("Couldn't place the handicap stones: {:?}", e);
+      return;
+    },
+  };
+  let mut last_move = None;
+  let mut clock = record.clock.clone().map(GameClock::new);
+  let mut turn_started = Instant::now();
+  clear_screen(mode);
+
   loop {
 
-    println!("{}", position);
-    println!("{:?}'s turn", position.turn);
+    if show_links {
+      println!("{}", position.render_links(mode));
+    } else {
+      match &last_move {
+        Some(i) => println!("{}", position.render(mode).highlight(i)),
+        None => println!("{}", position.render(mode)),
+      }
+    }
+    match &clock {
+      Some(i) if i.flagged().is_some() => println!("{}", i),
+      Some(i) => println!("{}\n{:?}'s turn", i, position.turn),
+      None => println!("{:?}'s turn", position.turn),
+    }
 
-    //Input your move in the format of "[int] [int]"
+    //Input your move in the format of "[int] [int]",
+    //"links" to toggle drawing links, "svg [file]" to export the position
+    //"svg-game [file]" and "svg-frames [prefix]" to export the whole game
+    //"preview [int] [int]" to see what a move would do
+    //or "swap" to take over the first move when the rules allow it.
+    //Before the first move the headers of a game record are accepted as well,
+    //"handicap [int] [int]", "swap", "size [int] [int]" and so on, see `GameRecord`
     let mut input = String::new();
 
-    if let Err(e) = io::stdin().read_line(&mut input){
-      println!("{}", e);
-      continue;
-    } else {
-      println!("{}[2J", 27 as char);
+    match io::stdin().read_line(&mut input) {
+      Err(e) => {
+        println!("{}", e);
+        continue;
+      },
+      Ok(0) => return,
+      Ok(_) => clear_screen(mode),
+    }
 
+    // until the first move, the headers of a game record set up the game, so that
+    // a whole record can be piped in
+    if record.moves.is_empty() {
+      if let Ok(setup) = format!("{}{}", record, input).parse::<GameRecord>() {
+        if setup.moves.is_empty() {
+          match setup.start() {
+            Ok(i) => {
+              log!(Level::Info, "cli", "set up the game with {}", input.trim());
+              position = i;
+              clock = setup.clock.clone().map(GameClock::new);
+              record = setup;
+              turn_started = Instant::now();
+            },
+            Err(e) => println!("Couldn't set up the game like that: {:?}", e),
+          }
+          continue;
+        }
+      }
     }
-    
+
     let mut iter = input.split_whitespace();
 
+    match iter.clone().next() {
+      Some(i) if i.starts_with('#') => continue,
+      Some("links") => {
+        show_links = !show_links;
+        continue;
+      },
+      Some("svg") => {
+        match iter.nth(1) {
+          Some(path) => if let Err(e) = std::fs::write(path, position.to_svg().to_string()) {
+            println!("Couldn't write {}: {}", path, e);
+          },
+          None => println!("Usage: svg [file]"),
+        }
+        continue;
+      },
+      Some("swap") => {
+        match position.swap() {
+          Ok(()) => {
+            log!(Level::Info, "cli", "white took over the first move");
+            record.swapped = true;
+            last_move = None;
+            // taking over the move is free, the time goes to the next move
+            turn_started = Instant::now();
+          },
+          Err(_) => println!("Swapping is only allowed as white's first move, when started with --swap"),
+        }
+        continue;
+      },
+      Some("preview") => {
+        match (iter.nth(1).map(str::parse), iter.next().map(str::parse)) {
+          (Some(Ok(x)), Some(Ok(y))) => preview(&position, &Location::new(x, y)),
+          _ => println!("Usage: preview [int] [int]"),
+        }
+        continue;
+      },
+      Some(command @ "svg-game") | Some(command @ "svg-frames") => {
+        match iter.nth(1) {
+          Some(path) => if let Err(e) = export_game(&record, path, command == "svg-game") {
+            println!("Couldn't write {}: {}", path, e);
+          },
+          None => println!("Usage: {} [file]", command),
+        }
+        continue;
+      },
+      _ => (),
+    }
+
+    if let Some(player) = clock.as_ref().and_then(|i| i.flagged()) {
+      println!("The game is over, {:?} lost on time", player);
+      continue;
+    }
+
     if let (Some(x), Some(y)) = (iter.next(), iter.next()) {
       if let (Ok(int_x), Ok(int_y)) = (x.parse(), y.parse()){
-        if let Err(_) = position.make_move(Location::new(int_x, int_y)){
-          println!("Couldn't put a piece there!");
+        let location = Location::new(int_x, int_y);
+        let player = position.turn.clone();
+        // kept to the millisecond, which is what records store
+        let elapsed = Duration::from_millis(turn_started.elapsed().as_millis() as u64);
+
+        // the clock is checked before the move is played, a move made too late does not count
+        if let Some(clock) = &mut clock {
+          let mut checked = clock.clone();
+          if position.legal_moves().contains(&location) && checked.spend(&player, elapsed).is_err() {
+            *clock = checked;
+            println!("{:?} ran out of time", player);
+            continue;
+          }
+        }
+
+        match position.make_move(location.clone()) {
+          Ok(outcome) => {
+            log!(Level::Info, "cli", "{}", outcome);
+            println!("{}", outcome);
+            last_move = Some(outcome);
+
+            match &mut clock {
+              Some(clock) => {
+                record.push_timed(location, elapsed);
+                clock.spend(&player, elapsed).ok();
+              },
+              None => record.push(location),
+            }
+            turn_started = Instant::now();
+          },
+          Err(e) => {
+            log!(Level::Warn, "cli", "illegal move {}: {:?}", location, e);
+            println!("Couldn't put a piece there!");
+          },
         }
       } else {
-        println!("Couldn't parse as integers");        
+        println!("Couldn't parse as integers");
       }
     } else {
-      println!("Invalid input format.");           
+      println!("Invalid input format.");
     }
   }
 }
-
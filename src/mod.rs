@@ -1,5 +0,0 @@
-pub mod main;
-pub mod position;
-pub mod location;
-pub mod board;
-pub mod engine;
\ No newline at end of file
@@ -0,0 +1,322 @@
+use std::io::ErrorKind;
+use std::str::FromStr;
+
+use crate::features;
+use crate::location::Location;
+use crate::position::Position;
+
+/// What an evaluator thinks of a position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+  /// How good the position is for the player to move, from -1 to 1.
+  pub value: f32,
+  /// How promising every legal move is, adding up to 1.
+  pub priors: Vec<(Location, f32)>,
+}
+
+/// Judges positions for a search.
+pub trait Evaluator {
+  fn evaluate(&self, position: &Position) -> Result<Evaluation, ErrorKind>;
+}
+
+/// Thinks every position is even and every legal move as good as the others,
+/// for searching without a network.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Uniform;
+
+impl Evaluator for Uniform {
+  fn evaluate(&self, position: &Position) -> Result<Evaluation, ErrorKind> {
+    let probability = 1.0 / position.legal_moves().len() as f32;
+    Ok(Evaluation {
+      value: 0.0,
+      priors: position.legal_moves().iter().map(|i| (i.to_owned(), probability)).collect(),
+    })
+  }
+}
+
+/// A fully connected layer. The hidden layers of a `Network` are followed by a ReLU.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dense {
+  pub inputs: usize,
+  pub outputs: usize,
+  /// The weights of every output in turn, `inputs` of them each.
+  pub weights: Vec<f32>,
+  pub biases: Vec<f32>,
+}
+
+impl Dense {
+  /// Creates a layer with all weights and biases zero.
+  pub fn new(inputs: usize, outputs: usize) -> Self {
+    Self {
+      inputs,
+      outputs,
+      weights: vec![0.0; inputs * outputs],
+      biases: vec![0.0; outputs],
+    }
+  }
+
+  /// Returns the weighted sums of the inputs for every output.
+  fn linear(&self, input: &[f32]) -> Vec<f32> {
+    self.weights
+      .chunks(self.inputs)
+      .zip(&self.biases)
+      .map(|(weights, bias)| weights.iter().zip(input).map(|(w, x)| w * x).sum::<f32>() + bias)
+      .collect()
+  }
+}
+
+/// A small multilayer perceptron on the planes of `features::encode`, with a value head
+/// and a policy head over the squares of the board. It runs on the CPU only.
+///
+/// As text it is a list of tokens separated by whitespace, with `#` starting a comment:
+///
+/// ```text
+/// connecticut-mlp
+/// board <size_x> <size_y>
+/// dense <inputs> <outputs> <weights> <biases>    (any number of hidden layers)
+/// value <inputs> <weights> <bias>
+/// policy <inputs> <weights> <biases>             (one output per square, row by row)
+/// ```
+///
+/// Weights are listed output by output. The first layer takes `features::PLANES`
+/// planes of the board and every layer takes the outputs of the one before it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Network {
+  pub size_x: usize,
+  pub size_y: usize,
+  pub hidden: Vec<Dense>,
+  /// A single output, squashed with tanh.
+  pub value: Dense,
+  /// One output per square, turned into priors over the legal moves with a softmax.
+  pub policy: Dense,
+}
+
+impl Network {
+  /// Creates a network with zero weights, for the given board size and hidden layer sizes.
+  pub fn new(size_x: usize, size_y: usize, hidden: &[usize]) -> Self {
+    let mut inputs = features::PLANES * size_x * size_y;
+    let mut layers = Vec::new();
+    for outputs in hidden {
+      layers.push(Dense::new(inputs, *outputs));
+      inputs = *outputs;
+    }
+
+    Self {
+      size_x,
+      size_y,
+      hidden: layers,
+      value: Dense::new(inputs, 1),
+      policy: Dense::new(inputs, size_x * size_y),
+    }
+  }
+
+  /// Reads a network from a file, see `Network` for the format.
+  pub fn load(path: &str) -> Result<Self, ErrorKind> {
+    std::fs::read_to_string(path).map_err(|e| e.kind())?.parse()
+  }
+
+  /// Checks that the board and every layer have a size, that every layer has as many
+  /// weights and biases as its size calls for and that the layers fit together.
+  fn check(&self) -> Result<(), ErrorKind> {
+    let mut layers = self.hidden.iter().chain([&self.value, &self.policy]);
+    if self.size_x == 0 || self.size_y == 0 || layers.any(|i| {
+      i.inputs == 0 || i.outputs == 0 || i.inputs.checked_mul(i.outputs) != Some(i.weights.len()) || i.biases.len() != i.outputs
+    }) {
+      return Err(ErrorKind::InvalidData);
+    }
+
+    let mut inputs = features::PLANES * self.size_x * self.size_y;
+    for layer in &self.hidden {
+      if layer.inputs != inputs {
+        return Err(ErrorKind::InvalidData);
+      }
+      inputs = layer.outputs;
+    }
+
+    let area = self.size_x * self.size_y;
+    if self.value.inputs != inputs || self.value.outputs != 1 || self.policy.inputs != inputs || self.policy.outputs != area {
+      return Err(ErrorKind::InvalidData);
+    }
+    Ok(())
+  }
+}
+
+impl Evaluator for Network {
+  /// Fails with `InvalidInput` for a board of another size than the network was made for,
+  /// and with `InvalidData` when the layers do not fit together.
+  fn evaluate(&self, position: &Position) -> Result<Evaluation, ErrorKind> {
+    if position.board.size_x != self.size_x || position.board.size_y != self.size_y {
+      return Err(ErrorKind::InvalidInput);
+    }
+    self.check()?;
+
+    let mut activations = features::encode(position);
+    for layer in &self.hidden {
+      activations = layer.linear(&activations).into_iter().map(|i| i.max(0.0)).collect();
+    }
+
+    let value = self.value.linear(&activations)[0].tanh();
+    let logits = self.policy.linear(&activations);
+
+    // a softmax over the legal moves only, sorted so the order does not change from run to run
+    let mut legal: Vec<Location> = position.legal_moves().iter().cloned().collect();
+    legal.sort_by_key(|i| (i.y, i.x));
+    let logit = |i: &Location| logits[i.y as usize * self.size_x + i.x as usize];
+    let max = legal.iter().map(logit).fold(f32::NEG_INFINITY, f32::max);
+    let exps: Vec<f32> = legal.iter().map(|i| (logit(i) - max).exp()).collect();
+    let sum: f32 = exps.iter().sum();
+
+    Ok(Evaluation {
+      value,
+      priors: legal.into_iter().zip(exps).map(|(i, e)| (i, e / sum)).collect(),
+    })
+  }
+}
+
+fn write_numbers(f: &mut std::fmt::Formatter<'_>, numbers: &[f32]) -> std::fmt::Result {
+  let numbers: Vec<String> = numbers.iter().map(|i| i.to_string()).collect();
+  writeln!(f, "{}", numbers.join(" "))
+}
+
+impl std::fmt::Display for Network {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "connecticut-mlp")?;
+    writeln!(f, "board {} {}", self.size_x, self.size_y)?;
+
+    for layer in &self.hidden {
+      writeln!(f, "dense {} {}", layer.inputs, layer.outputs)?;
+      write_numbers(f, &layer.weights)?;
+      write_numbers(f, &layer.biases)?;
+    }
+
+    for (name, layer) in [("value", &self.value), ("policy", &self.policy)] {
+      writeln!(f, "{} {}", name, layer.inputs)?;
+      write_numbers(f, &layer.weights)?;
+      write_numbers(f, &layer.biases)?;
+    }
+
+    Ok(())
+  }
+}
+
+impl FromStr for Network {
+  type Err = ErrorKind;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut tokens = s
+      .lines()
+      .map(|i| i.split('#').next().unwrap_or_default())
+      .flat_map(str::split_whitespace);
+
+    let mut next = || tokens.next().ok_or(ErrorKind::InvalidData);
+    fn number<T: FromStr>(token: &str) -> Result<T, ErrorKind> {
+      token.parse().map_err(|_| ErrorKind::InvalidData)
+    }
+
+    if next()? != "connecticut-mlp" || next()? != "board" {
+      return Err(ErrorKind::InvalidData);
+    }
+    let size_x: usize = number(next()?)?;
+    let size_y: usize = number(next()?)?;
+
+    let mut hidden = Vec::new();
+    let mut heads = Vec::new();
+    while heads.len() < 2 {
+      let kind = next()?;
+      let inputs = number(next()?)?;
+      let outputs = match kind {
+        "dense" if heads.is_empty() => number(next()?)?,
+        "value" if heads.is_empty() => 1,
+        "policy" if heads.len() == 1 => size_x * size_y,
+        _ => return Err(ErrorKind::InvalidData),
+      };
+
+      // read one by one rather than allocated up front, so a broken size fails on the missing numbers
+      let mut read = |n: usize| (0..n).map(|_| number(next()?)).collect::<Result<Vec<f32>, ErrorKind>>();
+      let layer = Dense {
+        inputs,
+        outputs,
+        weights: read(inputs.checked_mul(outputs).ok_or(ErrorKind::InvalidData)?)?,
+        biases: read(outputs)?,
+      };
+
+      match kind {
+        "dense" => hidden.push(layer),
+        _ => heads.push(layer),
+      }
+    }
+
+    if next().is_ok() {
+      return Err(ErrorKind::InvalidData);
+    }
+
+    let policy = heads.pop().ok_or(ErrorKind::InvalidData)?;
+    let value = heads.pop().ok_or(ErrorKind::InvalidData)?;
+    let network = Network { size_x, size_y, hidden, value, policy };
+    network.check()?;
+    Ok(network)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::ErrorKind;
+
+  use crate::features;
+  use crate::location::Location;
+  use crate::network::{Evaluator, Network};
+  use crate::position::Position;
+
+  #[test]
+  fn test_evaluate() {
+    let position = Position::from_diagram(". . .\n. . .\n. . .").unwrap();
+    let mut network = Network::new(3, 3, &[4]);
+
+    // without weights every legal move is as likely and the value is that of the bias
+    network.value.biases[0] = 0.5;
+    let evaluation = network.evaluate(&position).unwrap();
+    assert!((evaluation.value - 0.5f32.tanh()).abs() < 1e-6);
+    assert_eq!(evaluation.priors.len(), 8);
+    assert!(evaluation.priors.iter().all(|i| (i.1 - 0.125).abs() < 1e-6));
+
+    // a hidden unit that fires on the side to move, and favours the corner (2, 2)
+    network.hidden[0].weights[features::BLACK_TO_MOVE * 9] = 1.0;
+    network.policy.weights[8 * 4] = 3.0;
+    let evaluation = network.evaluate(&position).unwrap();
+    let best = evaluation.priors.iter().max_by(|a, b| a.1.partial_cmp(&b.1).unwrap()).unwrap();
+    assert_eq!(best.0, Location::new(2, 2));
+    assert!((evaluation.priors.iter().map(|i| i.1).sum::<f32>() - 1.0).abs() < 1e-5);
+
+    assert_eq!(network.evaluate(&Position::default()).unwrap_err(), ErrorKind::InvalidInput);
+  }
+
+  #[test]
+  fn test_network_file() {
+    let mut network = Network::new(3, 3, &[2, 2]);
+    network.hidden[1].weights[1] = -0.25;
+    network.policy.biases[4] = 1e-3;
+
+    let text = network.to_string();
+    assert_eq!(format!("# a comment\n{}", text).parse::<Network>().unwrap(), network);
+
+    assert!(text.replace("value 2", "value 3").parse::<Network>().is_err());
+    assert!(format!("{} 1", text).parse::<Network>().is_err());
+    assert!(text.replace("connecticut-mlp", "mlp").parse::<Network>().is_err());
+    assert!("connecticut-mlp board 3 3".parse::<Network>().is_err());
+
+    // layers without inputs or outputs, and boards without squares
+    assert!("connecticut-mlp board 1 1 dense 10 0 value 0 0 policy 0 0".parse::<Network>().is_err());
+    assert!(Network::new(0, 3, &[]).to_string().parse::<Network>().is_err());
+    let empty = Network::new(3, 3, &[0]);
+    let position = Position::from_diagram(". . .\n. . .\n. . .").unwrap();
+    assert_eq!(empty.evaluate(&position).unwrap_err(), ErrorKind::InvalidData);
+
+    // layers changed in place after they were made
+    let mut network = Network::new(3, 3, &[2]);
+    network.value.biases.clear();
+    assert_eq!(network.evaluate(&position).unwrap_err(), ErrorKind::InvalidData);
+    let mut network = Network::new(3, 3, &[2]);
+    network.hidden[0].weights.pop();
+    assert_eq!(network.evaluate(&position).unwrap_err(), ErrorKind::InvalidData);
+  }
+}
@@ -1,41 +1,31 @@
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::io::ErrorKind;
 
-#[cfg(feature = "with_cargo")]
-use board::{Board, BoardValue};
-#[cfg(feature = "with_cargo")]
-use location::Location;
-
-
-#[cfg(not(feature = "with_cargo"))]
-use src::board::{Board, BoardValue};
-#[cfg(not(feature = "with_cargo"))]
-use src::location::Location;
-
-#[derive(Debug, Clone)]
-struct EmptySquareError;
-impl std::fmt::Display for EmptySquareError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "Empty square found at position")
-    }
-}
-impl std::error::Error for EmptySquareError {}
-
-#[derive(Debug, Clone, PartialEq)]
+use crate::board::{Board, BoardValue};
+use crate::chain::Chains;
+use crate::diagram::Diagram;
+use crate::editor::Editor;
+use crate::geometry;
+use crate::effects::{MoveEffects, MoveOutcome};
+use crate::log::Level;
+use crate::group::{Group, Groups, UnionFind};
+use crate::location::Location;
+use crate::render::{LinkRenderer, Renderer, RenderMode};
+use crate::rules::{Blocking, Capture, RuleSet};
+use crate::svg::Svg;
+use crate::violation::Violation;
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum Player {
+  #[default]
   Black,
   White
 }
 
-impl Default for Player {
-  fn default() -> Self {
-    Player::Black
-  }
-}
-
 impl Player {
-  fn switch(&self) -> Self {
+  /// Returns the opponent of this player.
+  pub fn switch(&self) -> Self {
     match self {
       Player::White => Player::Black,
       _ => Player::White
@@ -43,292 +33,905 @@ impl Player {
   } 
 }
 
+/// Stones put on and taken off the board without changing it, to look at the board as it
+/// would be after a move. The default overlay changes nothing.
+#[derive(Debug, Default)]
+struct Overlay {
+  /// The placed stone and its value on the board.
+  placed: Option<(Location, BoardValue)>,
+  /// The stones the placed stone links to.
+  links: Vec<Location>,
+  /// The captured stones.
+  removed: Vec<Location>,
+}
+
+impl Overlay {
+  /// Returns an overlay with a stone of a player on a location, linked to the given stones.
+  fn placing(location: &Location, player: &Player, links: Vec<Location>) -> Self {
+    Self {
+      placed: Some((location.to_owned(), BoardValue::Filled(player.to_owned()))),
+      links,
+      removed: Vec::new(),
+    }
+  }
+
+  fn get_at<'b>(&'b self, board: &'b Board, location: &Location) -> Result<&'b BoardValue, ErrorKind> {
+    match &self.placed {
+      Some((placed, value)) if placed == location => Ok(value),
+      _ if self.removed.contains(location) => Ok(&BoardValue::Empty),
+      _ => board.get_at(location),
+    }
+  }
+
+  fn is_color_at(&self, board: &Board, location: &Location, player: &Player) -> bool {
+    self.get_at(board, location).is_ok_and(|i| i.is_color(player))
+  }
+
+  fn blocks(&self, board: &Board, location: &Location, player: &Player) -> bool {
+    self.get_at(board, location).is_ok_and(|i| i.blocks(player))
+  }
+
+  /// Returns the stones the placed stone links to, when it is on the location.
+  fn links_of(&self, location: &Location) -> impl Iterator<Item = &Location> {
+    let placed = matches!(&self.placed, Some((i, _)) if i == location);
+    self.links.iter().filter(move |_| placed)
+  }
+}
+
+#[derive(Debug, Clone)]
 pub struct Position {
   pub board: Board,
   pub turn: Player,
 
   pub legal_white_moves: HashSet<Location>,
   pub legal_black_moves: HashSet<Location>,
+
+  chains: Chains,
+  rules: RuleSet,
+  moves_played: usize,
 }
 
 impl Position{
-  /// Switches the current active player
-  fn next_turn(&mut self){
-    self.turn = self.turn.switch();
+  /// Creates a position with black to move, played on the given board by the given rules.
+  /// The board may have holes and neutral stones, but no stones of the players,
+  /// those are added by playing moves.
+  /// Fails with `InvalidInput` when the rules can't be played on the board, see `check_rules`.
+  pub fn new(board: Board, rules: RuleSet) -> Result<Self, ErrorKind> {
+    Self::check_rules(&board, &rules)?;
+    Ok(Self::new_unchecked(board, rules))
   }
 
-  /// Returns the legal moves set of the current active player
-  fn legal_moves(&mut self) -> &mut HashSet<Location> {
-    match self.turn {
-      Player::Black => &mut self.legal_black_moves,
-      _ => &mut self.legal_white_moves
+  /// Like `new`, for positions that are checked later on, as an `Editor` does.
+  pub(crate) fn new_unchecked(board: Board, rules: RuleSet) -> Self {
+    let anchors = |player: &Player| {
+      let mut result = HashSet::new();
+      for x in 0..board.size_x {
+        for y in 0..board.size_y {
+          let location = Location::new(x as isize, y as isize);
+          if rules.anchors.contains(&board, &location, player) && board.get_at(&location) == Ok(&BoardValue::Empty) {
+            result.insert(location);
+          }
+        }
+      }
+      result
+    };
+
+    Self{
+      turn: Player::default(),
+
+      legal_white_moves: anchors(&Player::White),
+      legal_black_moves: anchors(&Player::Black),
+      board,
+
+      chains: Chains::new(),
+      rules,
+      moves_played: 0,
     }
   }
 
-  pub fn make_move(&mut self, location: Location) -> Result<(), ErrorKind>{
-
-    // move calidation
-    if !self.legal_moves().contains(&location) {
+  /// Checks that a board can be played on by a set of rules. A wrapped axis has to be at
+  /// least twice as long as a link reaches, plus one, or a link could reach a square both
+  /// ways around, and both players need an anchor square that a stone can be put on.
+  pub(crate) fn check_rules(board: &Board, rules: &RuleSet) -> Result<(), ErrorKind> {
+    let shortest = 2 * rules.links.reach() as usize + 1;
+    if board.topology.wraps_x() && board.size_x < shortest || board.topology.wraps_y() && board.size_y < shortest {
+      log!(
+        Level::Debug, "position", "rejected a {}x{} {:?} board, wrapped sides need {} squares",
+        board.size_x, board.size_y, board.topology, shortest
+      );
       return Err(ErrorKind::InvalidInput);
     }
 
-    // piece insertion
-    self.board.insert_piece(&self.turn, &location)?;
+    for player in &[Player::Black, Player::White] {
+      let anchored = (0..board.size_x)
+        .flat_map(|x| (0..board.size_y).map(move |y| Location::new(x as isize, y as isize)))
+        .any(|i| {
+          rules.anchors.contains(board, &i, player) &&
+          matches!(board.get_at(&i), Ok(BoardValue::Empty) | Ok(BoardValue::Filled(_)))
+        });
+      if !anchored {
+        log!(Level::Debug, "position", "rejected the rules, {:?} has no anchor square", player);
+        return Err(ErrorKind::InvalidInput);
+      }
+    }
 
-    // updating of legal moves
-    for i in self.get_reachable(&location, &self.turn.to_owned()) {
-      self.legal_moves().insert(i.to_owned());
-    };
+    Ok(())
+  }
 
-    self.legal_white_moves.remove(&location);
-    self.legal_black_moves.remove(&location);
+  /// Creates a handicap game: black starts with stones on the given squares and white
+  /// moves first. Without stones it is an ordinary game.
+  /// Fails with `InvalidData` when a stone would have no anchor.
+  pub fn with_handicap(board: Board, rules: RuleSet, stones: &[Location]) -> Result<Self, ErrorKind> {
+    let mut editor = Editor::new(board, rules);
+    for stone in stones {
+      editor.place(stone, &Player::Black)?;
+    }
+    if !stones.is_empty() {
+      editor.set_turn(&Player::White);
+    }
+    editor.finish()
+  }
 
-    match self.turn {
-      Player::Black => self.get_cut_off_squares(&location)?
-        .iter()
-        .for_each(|i| {self.legal_white_moves.remove(i);}),
-      _ => self.get_cut_off_squares(&location)?
-        .iter()
-        .for_each(|i| {self.legal_black_moves.remove(i);}),
-    }   
+  /// Sets up a position with the default rules from a text diagram, see `Diagram`.
+  pub fn from_diagram(diagram: &str) -> Result<Self, ErrorKind> {
+    diagram.parse::<Diagram>()?.position(RuleSet::default())
+  }
 
-    // capturing
-    let cloud = self.get_connection_cloud(&location)?;
-    for coordinate in cloud
-      .iter()
-      .filter(|i| self.board.is_color_at(i, &self.turn.switch()))
-      .collect::<Vec<&Location>>(){
-      dbg!(&coordinate);
+  /// Starts editing this position, see `Editor`.
+  pub fn edit(self) -> Editor {
+    Editor::from_position(self)
+  }
 
-      if let Some(pieces_to_remove) = self.connection_to_edge(coordinate)?{
-        self.capture_piece(&coordinate, pieces_to_remove)?;
-      }
+  /// Returns how many moves have been played, a swap included.
+  pub fn moves_played(&self) -> usize {
+    self.moves_played
+  }
+
+  /// Lets white take over the first move of black when the rules allow swapping:
+  /// the stone becomes white and it is black's turn again.
+  /// Fails when this is not the second move of a game with the swap rule.
+  pub fn swap(&mut self) -> Result<(), ErrorKind> {
+    if !self.rules.swap || self.moves_played != 1 || self.turn != Player::White {
+      return Err(ErrorKind::InvalidInput);
     }
 
-    // wrapping up
-    self.next_turn();
+    let stones: Vec<Location> = self.chains.iter().flat_map(|i| i.stones.iter().cloned()).collect();
+    let first = match stones.as_slice() {
+      [i] => i.to_owned(),
+      _ => return Err(ErrorKind::InvalidInput),
+    };
+
+    let mut editor = self.clone().edit();
+    editor.place(&first, &Player::White)?;
+    editor.set_turn(&Player::Black);
 
+    *self = editor.finish()?;
+    self.moves_played += 1;
+    log!(Level::Debug, "position", "white took over the first move at {}", first);
     Ok(())
   }
 
+  pub fn rules(&self) -> &RuleSet {
+    &self.rules
+  }
 
-  /// Handles all the logic behind capturing a piece and updating the legal moves sets
-  fn capture_piece(&mut self, _location: &Location, pieces_to_remove: HashSet<Location>) -> Result<(), ErrorKind> {
-    for piece in pieces_to_remove{
-      self.board.clear_at(&piece)?;
-    }
+  /// Returns whether a location is an anchor square for a player under the rules,
+  /// whether or not there is a stone on it.
+  pub fn is_anchor_square(&self, location: &Location, player: &Player) -> bool {
+    self.rules.anchors.contains(&self.board, location, player)
+  }
 
-    // Add locations of fallen pieces to legal moves of both black and white
-    // Remove positions previously reachable from fallen pieces from legal moves
-    // Idea: possibly do these together by iterating over all the locations of fallen pieces once.
-    // more?
+  /// Returns a displayable view of this position in the given render mode.
+  pub fn render(&self, mode: RenderMode) -> Renderer<'_> {
+    Renderer::new(self, mode)
+  }
 
-    Ok(())
+  /// Like `render`, but spreads the squares out and draws the links between stones.
+  pub fn render_links(&self, mode: RenderMode) -> LinkRenderer<'_> {
+    LinkRenderer::new(self, mode)
   }
 
-  /// Returns all the squares around a placed piece,
-  ///  that used to be accessible from other places around the placed piece,
-  /// but are now blocked off by this placed piece.
-  fn get_cut_off_squares(&mut self, location: &Location) -> Result<Vec<Location>, ErrorKind> {
-    let other_player = match self.board.get_at(location)?{
-      BoardValue::Filled(i) => i.switch(),
-      _ => return Err(ErrorKind::InvalidInput),
-    };
+  /// Returns a displayable SVG document of this position, links included.
+  pub fn to_svg(&self) -> Svg<'_> {
+    Svg::new(self)
+  }
 
-    let empty = BoardValue::Empty;
+  /// Returns every link between two stones of the given player, each link once.
+  pub fn links(&self, player: &Player) -> Vec<(Location, Location)> {
+    let mut result = Vec::new();
 
-    Ok(self.get_connection_cloud(location)?
-      .iter()
-      .filter(|i| {
-        self.board.get_at(i) == Ok(&empty) &&
-        self.get_reachable(&i, &other_player)
-          .iter()
-          .filter(|i| self.board.is_color_at(&i, &other_player))
-          .peekable() // is empty
-          .peek()
-          .is_none()
-      })
-      .cloned()
-      .collect())
+    for x in 0..self.board.size_x {
+      for y in 0..self.board.size_y {
+        let from = Location::new(x as isize, y as isize);
+        if !self.board.is_color_at(&from, player) {
+          continue;
+        }
+
+        result.extend(
+          self.get_reachable(&from, player)
+            .into_iter()
+            .filter(|to| from < *to && self.board.is_color_at(to, player))
+            .map(|to| (from.clone(), to))
+        );
+      }
+    }
+
+    result
+  }
+
+  /// Returns the squares the current active player may play on.
+  pub fn legal_moves(&self) -> &HashSet<Location> {
+    self.legal_moves_for(&self.turn)
   }
 
-  /// Returns whether or not a piece is connected to the edge in any way. 
-  /// Uses a depth first approch.
-  /// returns either None (the piece was connected to the edge)
-  /// or Some(HashSet<Location>) indicating the piece is not connecting to the edge.
-  /// In this case, the returned hashset is a history of all pieces it visited during /// it's DFS. All of these pieces are definitely not connected to the edge and can 
-  /// therefore with certainty be removed from the game.
-  fn connection_to_edge(&self, location: &Location) -> Result<Option<HashSet<Location>>, ErrorKind>{
-    let player = match self.board.get_at(location)?{
+  /// Returns the squares the given player may play on,
+  /// regardless of whose turn it is.
+  pub fn legal_moves_for(&self, player: &Player) -> &HashSet<Location> {
+    match player {
+      Player::Black => &self.legal_black_moves,
+      Player::White => &self.legal_white_moves
+    }
+  }
+
+  /// Returns the stones of the same colour that the stone at a location links to.
+  /// Fails when there is no stone at the location.
+  pub fn linked_stones(&self, location: &Location) -> Result<Vec<Location>, ErrorKind> {
+    let player = match self.board.get_at(location)? {
       BoardValue::Filled(i) => i,
       _ => return Err(ErrorKind::InvalidInput),
     };
 
+    Ok(self.get_reachable(location, player)
+      .into_iter()
+      .filter(|i| self.board.is_color_at(i, player))
+      .collect())
+  }
 
-    let mut visited: HashSet<Location> = HashSet::new();
-    let mut trace_stack: Vec<Location> = Vec::new();
+  /// Returns all stones that are linked, directly or through other stones,
+  /// to the stone at a location, that stone included.
+  /// Fails when there is no stone at the location.
+  pub fn group(&self, location: &Location) -> Result<HashSet<Location>, ErrorKind> {
+    match self.chains.get(location) {
+      Some(i) => Ok(i.stones.clone()),
+      None => Err(ErrorKind::InvalidInput),
+    }
+  }
 
-    trace_stack.push(location.to_owned());
+  /// Returns whether the group of the stone at a location is linked to an anchor.
+  /// Fails when there is no stone at the location.
+  pub fn is_anchored(&self, location: &Location) -> Result<bool, ErrorKind> {
+    match self.chains.get(location) {
+      Some(i) => Ok(i.is_anchored()),
+      None => Err(ErrorKind::InvalidInput),
+    }
+  }
 
-    while let Some(coordinate) = trace_stack.pop() {
-      
-      if visited.contains(&coordinate){
-        continue;
+  /// Partitions all stones on the board into groups of linked stones.
+  pub fn groups(&self) -> Groups {
+    let mut stones = Vec::new();
+    for x in 0..self.board.size_x {
+      for y in 0..self.board.size_y {
+        let location = Location::new(x as isize, y as isize);
+        if let Ok(BoardValue::Filled(player)) = self.board.get_at(&location) {
+          stones.push((location, player.to_owned()));
+        }
       }
+    }
 
-      if 
-        coordinate.x == 0 || 
-        coordinate.x == self.board.size_x as isize - 1 || 
-        coordinate.y == 0 || 
-        coordinate.y == self.board.size_y as isize - 1 {
-        return Ok(None);
+    let index: HashMap<&Location, usize> = stones
+      .iter()
+      .enumerate()
+      .map(|(i, (location, _))| (location, i))
+      .collect();
+
+    let mut sets = UnionFind::new(stones.len());
+    for player in &[Player::Black, Player::White] {
+      for (from, to) in self.links(player) {
+        sets.union(index[&from], index[&to]);
       }
+    }
 
-      visited.insert(coordinate.to_owned());
-
-      trace_stack.extend(
-        self.get_reachable(&coordinate, player)
-        .iter()
-        .filter(|i| self.board.is_color_at(i, player))
-        .filter(|i| !visited.contains(i))
-        .cloned()
-      )
+    let mut groups: Vec<Group> = Vec::new();
+    let mut group_of_root = HashMap::new();
+
+    for (i, (location, player)) in stones.iter().enumerate() {
+      let group = *group_of_root.entry(sets.find(i)).or_insert_with(|| {
+        groups.push(Group {
+          player: player.to_owned(),
+          stones: HashSet::new(),
+          anchored: false,
+          liberties: HashSet::new(),
+        });
+        groups.len() - 1
+      });
+      let group = &mut groups[group];
+
+      group.stones.insert(location.to_owned());
+      group.anchored |= self.is_anchor_square(location, player);
+      group.liberties.extend(
+        self.get_reachable(location, player)
+          .into_iter()
+          .filter(|i| self.board.get_at(i) == Ok(&BoardValue::Empty))
+      );
     }
 
-    return Ok(Some(visited));
+    Groups::new(groups)
   }
 
-  /// Calculates and returns for all pieces of around a location which are the opposite
-  /// color to the piece, the locations that would be blocked by the placing of the
-  /// piece at the location. This can be used to check if pieces in these places should 
-  /// fall, or be removed from the legal moves sets.
-  fn get_connection_cloud(&self, location: &Location) -> Result<Vec<Location>, ErrorKind> {
+  /// Returns the links the chains are made of, each link once.
+  /// Unlike `links` this is what was linked, not what the board allows.
+  pub(crate) fn chain_links(&self) -> Vec<(Location, Location)> {
+    let mut result = Vec::new();
+    for from in self.chains.iter().flat_map(|i| i.stones.iter()) {
+      result.extend(self.chains.links_of(from).filter(|to| from < *to).map(|to| (from.to_owned(), to.to_owned())));
+    }
+    result
+  }
 
-    let mut result = HashSet::new();
-    
-    let result_vec: Vec<Location> = {
-      let other_player = match self.board.get_at(location)?{
-        BoardValue::Filled(i) => i.switch(),
+  /// Rebuilds the chains and legal moves sets from the stones on the board.
+  ///
+  /// The given links are kept as far as the board still allows them. The placed stones
+  /// are then linked in order as if they were played, and the other stones last, to pick
+  /// up links that removed stones were in the way of. The order only matters when links
+  /// can cross, so an unchanged position keeps the links it had.
+  pub(crate) fn rebuild(&mut self, links: &[(Location, Location)], placed: &[Location]) -> Result<(), ErrorKind> {
+    self.chains = Chains::new();
+
+    let mut kept = Vec::new();
+    for x in 0..self.board.size_x {
+      for y in 0..self.board.size_y {
+        let location = Location::new(x as isize, y as isize);
+        if let Ok(BoardValue::Filled(player)) = self.board.get_at(&location) {
+          if !placed.contains(&location) {
+            let player = player.to_owned();
+            self.chains.add_stone(&location, &player, self.is_anchor_square(&location, &player));
+            kept.push(location);
+          }
+        }
+      }
+    }
+
+    // a crossing link only gets in the way of links made after it, so those are kept as they are
+    for (from, to) in links {
+      let player = match self.board.get_at(from) {
+        Ok(BoardValue::Filled(i)) if self.board.is_color_at(to, i) => i.to_owned(),
+        _ => continue,
+      };
+      if self.rules.blocking == Blocking::Crossing || self.is_connection_between(from, to, &player)? {
+        self.chains.add_link(from, to);
+      }
+    }
+
+    for stone in placed {
+      let player = match self.board.get_at(stone)? {
+        BoardValue::Filled(i) => i.to_owned(),
         _ => return Err(ErrorKind::InvalidInput),
       };
+      let anchor = self.is_anchor_square(stone, &player);
+
+      // linking happens as if the stones were played, to the stones placed before
+      let linked: Vec<Location> = self.linked_stones(stone)?
+        .into_iter()
+        .filter(|i| self.chains.id_of(i).is_some())
+        .collect();
+      self.chains.add_stone(stone, &player, anchor);
+      for i in linked {
+        self.chains.add_link(stone, &i);
+      }
+    }
+
+    for stone in &kept {
+      for i in self.linked_stones(stone)? {
+        self.chains.add_link(stone, &i);
+      }
+    }
+
+    self.legal_black_moves.clear();
+    self.legal_white_moves.clear();
+    let mut effects = MoveEffects::default();
+    for x in 0..self.board.size_x {
+      for y in 0..self.board.size_y {
+        self.update_legality(&Location::new(x as isize, y as isize), &mut effects);
+      }
+    }
+
+    Ok(())
+  }
 
-      let proxy = self.board.proxy(location);
+  /// Checks that the board, the legal moves and the chains agree with each other,
+  /// and returns everything that is wrong. Legal moves are only checked to be empty
+  /// squares, so custom ones set up in an `Editor` are fine.
+  pub fn validate(&self) -> Result<(), Vec<Violation>> {
+    let board = &self.board;
+    let mut violations = Vec::new();
 
-      if proxy.is_color_at(&Location::new(-2, 0), &other_player) {
-        result.insert(( 0, 1));
-        result.insert(( 0, -1));
+    if board.board.len() != board.size_x {
+      violations.push(Violation::Width { size_x: board.size_x, columns: board.board.len() });
+    }
+    for (x, column) in board.board.iter().enumerate() {
+      if column.len() != board.size_y {
+        violations.push(Violation::Height { size_y: board.size_y, x, squares: column.len() });
       }
-      if proxy.is_color_at(&Location::new(-1, 1), &other_player) {
-        result.insert(( 0,-1));
-        result.insert(( 1, 0));
+    }
+
+    // everything else looks at squares, which can't be done on a board of the wrong shape
+    if !violations.is_empty() {
+      return Err(violations);
+    }
+
+    for player in &[Player::Black, Player::White] {
+      for location in self.legal_moves_for(player) {
+        if board.get_at(location) != Ok(&BoardValue::Empty) {
+          violations.push(Violation::IllegalSquare { player: player.to_owned(), location: location.to_owned() });
+        }
       }
-      if proxy.is_color_at(&Location::new(-1, 0), &other_player) {
-        result.insert(( 1, 1));
-        result.insert(( 1,-1));
-        result.insert(( 0, 2));
-        result.insert(( 0,-2));
+    }
+
+    for chain in self.chains.iter() {
+      for stone in &chain.stones {
+        if !board.is_color_at(stone, &chain.player) {
+          violations.push(Violation::StaleChain { location: stone.to_owned() });
+        }
       }
-      
-      if proxy.is_color_at(&Location::new(-1,-1), &other_player) {
-        result.insert(( 1, 0));
-        result.insert(( 0, 1));
+    }
+
+    for group in &self.groups() {
+      for stone in &group.stones {
+        match self.chains.get(stone) {
+          Some(chain) if chain.player == group.player => if chain.stones != group.stones {
+            violations.push(Violation::WrongChain {
+              location: stone.to_owned(),
+              expected: group.size(),
+              found: chain.stones.len(),
+            });
+          },
+          _ => violations.push(Violation::MissingChain { location: stone.to_owned() }),
+        }
       }
 
-      if proxy.is_color_at(&Location::new( 0, 2), &other_player) {
-        result.insert((-1, 0));
-        result.insert(( 1, 0));
+      if self.rules.capture == Capture::Unanchored && !group.anchored {
+        if let Some(stone) = group.stones.iter().next() {
+          violations.push(Violation::Uncaptured { player: group.player.to_owned(), location: stone.to_owned() });
+        }
       }
+    }
+
+    if violations.is_empty() {
+      Ok(())
+    } else {
+      Err(violations)
+    }
+  }
+
+  /// Switches the current active player
+  fn next_turn(&mut self){
+    self.turn = self.turn.switch();
+  }
+
+  /// Adds a square to or removes it from the legal moves set of a player,
+  /// and notes it in the effects of the move when the set changed.
+  fn set_legal(&mut self, player: &Player, location: &Location, legal: bool, effects: &mut MoveEffects) {
+    let (moves, changes) = match player {
+      Player::Black => (&mut self.legal_black_moves, &mut effects.black),
+      Player::White => (&mut self.legal_white_moves, &mut effects.white),
+    };
 
-      if proxy.is_color_at(&Location::new( 0, 1), &other_player) {
-        result.insert((-1,-1));
-        result.insert(( 1,-1));
-        result.insert((-2, 0));
-        result.insert(( 2, 0));
+    if legal {
+      if moves.insert(location.to_owned()) {
+        changes.add(location);
       }
-      
-      if proxy.is_color_at(&Location::new( 0,-1), &other_player) {
-        result.insert(( 1, 1));
-        result.insert((-1, 1));
-        result.insert((-2, 0));
-        result.insert(( 2, 0));
+    } else if moves.remove(location) {
+      changes.remove(location);
+    }
+  }
+
+  /// Plays a move for the current active player and returns what happened.
+  /// Fails when the location is not a legal move.
+  pub fn make_move(&mut self, location: Location) -> Result<MoveOutcome, ErrorKind>{
+    let player = self.turn.to_owned();
+    let effects = self.play(location.to_owned())?;
+    self.moves_played += 1;
+
+    #[cfg(debug_assertions)]
+    if let Err(violations) = self.validate() {
+      let violations: Vec<String> = violations.iter().map(|i| i.to_string()).collect();
+      panic!("{:?} playing {} left the position invalid: {}", player, location, violations.join(", "));
+    }
+
+    Ok(MoveOutcome {
+      location,
+      player,
+      effects,
+      next_turn: self.turn.to_owned(),
+    })
+  }
+
+  /// Returns what playing a move would do, without changing this position.
+  /// The board after the move is looked at through an overlay with the stone on it
+  /// and the captured stones gone. Fails when the location is not a legal move.
+  pub fn simulate_move(&self, location: &Location) -> Result<MoveEffects, ErrorKind> {
+    if !self.legal_moves().contains(location) {
+      return Err(ErrorKind::InvalidInput);
+    }
+
+    let player = self.turn.to_owned();
+    let other_player = player.switch();
+    let mut effects = MoveEffects::default();
+
+    let reachable = self.get_reachable(location, &player);
+    let linked = reachable.iter().filter(|i| self.board.is_color_at(i, &player)).cloned().collect();
+    let mut overlay = Overlay::placing(location, &player, linked);
+
+    // the legal moves change in the same order as in `play`
+    for i in &reachable {
+      if self.board.get_at(i) == Ok(&BoardValue::Empty) {
+        self.preview_legal(&player, i, true, &mut effects);
       }
+    }
+    self.preview_legal(&Player::Black, location, false, &mut effects);
+    self.preview_legal(&Player::White, location, false, &mut effects);
+    for i in self.get_cut_off_squares_with(location, &overlay)? {
+      self.preview_legal(&other_player, &i, false, &mut effects);
+    }
 
-      if proxy.is_color_at(&Location::new( 0,-2), &other_player) {
-        result.insert((-1, 0));
-        result.insert(( 1, 0));
+    for coordinate in self.get_connection_cloud_with(location, &overlay)?
+      .iter()
+      .filter(|i| self.board.is_color_at(i, &other_player)){
+      for linked in self.chains.links_of(coordinate) {
+        let link = if linked < coordinate {
+          (linked.to_owned(), coordinate.to_owned())
+        } else {
+          (coordinate.to_owned(), linked.to_owned())
+        };
+        if !effects.cut_links.contains(&link) && !self.is_connection_between_with(coordinate, linked, &other_player, &overlay)? {
+          effects.cut_links.push(link);
+        }
       }
+    }
+
+    if self.rules.capture == Capture::Unanchored {
+      for coordinate in effects.cut_links.iter().flat_map(|(from, to)| [from, to]) {
+        if effects.captured.iter().any(|i| i.contains(coordinate)) {
+          continue;
+        }
 
-      if proxy.is_color_at(&Location::new( 1, 1), &other_player) {
-        result.insert(( 0,-1));
-        result.insert((-1, 0));
+        let (stones, anchored) = self.connection_to_edge(coordinate, &effects.cut_links);
+        if !anchored {
+          effects.captured.push(stones);
+        }
       }
+    }
 
-      if proxy.is_color_at(&Location::new( 1, 0), &other_player) {
-        result.insert((-1, 1));
-        result.insert((-1,-1));
-        result.insert(( 0, 2));
-        result.insert(( 0,-2));
+    // the squares around the captured stones are looked at on the board without them
+    let mut affected = HashSet::new();
+    for piece in effects.captured_stones() {
+      affected.extend(self.get_connection_cloud_with(piece, &overlay)?);
+      affected.extend(self.connections_around(piece));
+      affected.insert(piece.to_owned());
+    }
+    overlay.removed = effects.captured_stones().cloned().collect();
+    for coordinate in &affected {
+      for player in &[Player::Black, Player::White] {
+        let legal = self.is_legal_for(coordinate, player, &overlay);
+        self.preview_legal(player, coordinate, legal, &mut effects);
       }
+    }
+
+    Ok(effects)
+  }
+
+  /// Notes in the effects of a previewed move that a square becomes legal or illegal
+  /// for a player, when that changes the legal moves set together with the effects so far.
+  fn preview_legal(&self, player: &Player, location: &Location, legal: bool, effects: &mut MoveEffects) {
+    let changes = match player {
+      Player::Black => &mut effects.black,
+      Player::White => &mut effects.white,
+    };
+
+    let was_legal = match self.legal_moves_for(player).contains(location) {
+      true => !changes.removed.contains(location),
+      false => changes.added.contains(location),
+    };
+    if legal && !was_legal {
+      changes.add(location);
+    } else if !legal && was_legal {
+      changes.remove(location);
+    }
+  }
+
+  /// Returns the stones linked, directly or through other stones, to the stone at a location
+  /// once the given links are cut, and whether any of them is on an anchor square, which is
+  /// the edge of the board under the default rules.
+  fn connection_to_edge(&self, location: &Location, cut_links: &[(Location, Location)]) -> (HashSet<Location>, bool) {
+    let player = match self.chains.get(location) {
+      Some(i) => i.player.to_owned(),
+      None => return (HashSet::new(), false),
+    };
 
-      if proxy.is_color_at(&Location::new( 1,-1), &other_player) {
-        result.insert(( 0, 1));
-        result.insert((-1, 0));
+    let mut stones = HashSet::from([location.to_owned()]);
+    let mut stack = vec![location.to_owned()];
+    let mut anchored = false;
+
+    while let Some(stone) = stack.pop() {
+      anchored |= self.is_anchor_square(&stone, &player);
+      for linked in self.chains.links_of(&stone) {
+        let cut = cut_links.iter().any(|(from, to)| {
+          (from == &stone && to == linked) || (to == &stone && from == linked)
+        });
+        if !cut && stones.insert(linked.to_owned()) {
+          stack.push(linked.to_owned());
+        }
       }
+    }
+
+    (stones, anchored)
+  }
 
-      if proxy.is_color_at(&Location::new( 2, 0), &other_player) {
-        result.insert(( 0, 1));
-        result.insert(( 0,-1));
-      } 
+  /// Plays a move for the current active player and records what it changed.
+  fn play(&mut self, location: Location) -> Result<MoveEffects, ErrorKind>{
 
-      result
+    // move calidation
+    if !self.legal_moves().contains(&location) {
+      log!(Level::Debug, "position", "rejected {:?} move at {}, not a legal square", self.turn, location);
+      return Err(ErrorKind::InvalidInput);
+    }
+
+    log!(Level::Trace, "position", "placing {:?} stone at {}", self.turn, location);
+
+    let mut effects = MoveEffects::default();
+    let player = self.turn.to_owned();
+    let other_player = player.switch();
+
+    // piece insertion
+    self.board.insert_piece(&self.turn, &location)?;
+    self.chains.add_stone(&location, &self.turn, self.is_anchor_square(&location, &self.turn));
+    for i in self.linked_stones(&location)? {
+      self.chains.add_link(&location, &i);
+    }
+
+    // updating of legal moves
+    for i in self.get_reachable(&location, &player) {
+      if self.board.get_at(&i) == Ok(&BoardValue::Empty) {
+        self.set_legal(&player, &i, true, &mut effects);
+      }
+    };
+
+    self.set_legal(&Player::Black, &location, false, &mut effects);
+    self.set_legal(&Player::White, &location, false, &mut effects);
+
+    for i in self.get_cut_off_squares(&location)? {
+      self.set_legal(&other_player, &i, false, &mut effects);
+    }
+
+    // cutting links, only links of the stones in the cloud can pass through the new piece
+    let mut cut_links = Vec::new();
+    for coordinate in self.get_connection_cloud(&location)?
+      .iter()
+      .filter(|i| self.board.is_color_at(i, &other_player)){
+      for linked in self.chains.links_of(coordinate) {
+        if !self.is_connection_between(coordinate, linked, &other_player)? {
+          cut_links.push((coordinate.to_owned(), linked.to_owned()));
+        }
+      }
+    }
+
+    // a link between two stones in the cloud is found from both of its ends
+    let mut seen = HashSet::new();
+    let cut_links: Vec<(Location, Location)> = cut_links
+      .into_iter()
+      .map(|(from, to)| if to < from { (to, from) } else { (from, to) })
+      .filter(|i| seen.insert(i.to_owned()))
+      .collect();
+    for (from, to) in &cut_links {
+      let split = self.chains.cut_link(from, to);
+      log!(
+        Level::Trace, "position", "cut link {} - {}{}",
+        from, to, if split.is_some() { ", splitting its chain" } else { "" }
+      );
+    }
+
+    // capturing, only the chains that lost a link can have lost their anchor
+    let mut captured = Vec::new();
+    let capturing = self.rules.capture == Capture::Unanchored;
+    for coordinate in cut_links.iter().flat_map(|(from, to)| vec![from, to]) {
+      if let (Some(id), Some(chain)) = (self.chains.id_of(coordinate), self.chains.get(coordinate)) {
+        if captured.contains(&id) {
+          continue;
+        }
+
+        let capture = capturing && !chain.is_anchored();
+        log!(
+          Level::Trace, "position", "chain of {} has {} stones and {} anchors, {}",
+          coordinate, chain.stones.len(), chain.anchors,
+          if capture { "capturing it" } else { "keeping it" }
+        );
+        if capture {
+          captured.push(id);
+        }
+      }
+    }
+
+    for chain in captured {
+      let stones = self.capture_piece(chain, &mut effects)?;
+      effects.captured.push(stones);
+    }
+    effects.cut_links = cut_links;
+
+    // wrapping up
+    self.next_turn();
+
+    for (player, changes) in &[(Player::Black, &effects.black), (Player::White, &effects.white)] {
+      log!(
+        Level::Debug, "position", "{:?} legal squares: {} added, {} removed, {} left",
+        player, changes.added.len(), changes.removed.len(), self.legal_moves_for(player).len()
+      );
+    }
+
+    Ok(effects)
+  }
+
+
+  /// Handles all the logic behind capturing a chain and updating the legal moves sets,
+  /// noting the changes in the effects of the move. Returns the stones that were removed.
+  fn capture_piece(&mut self, chain: usize, effects: &mut MoveEffects) -> Result<HashSet<Location>, ErrorKind> {
+    let (pieces_to_remove, player) = match self.chains.chain(chain) {
+      Some(i) => (i.stones.clone(), i.player.to_owned()),
+      None => return Ok(HashSet::new()),
+    };
+
+    // The squares that can change: the fallen pieces themselves, the squares they could
+    // link to and the squares they blocked off for the other player.
+    let mut affected = HashSet::new();
+    for piece in &pieces_to_remove {
+      affected.extend(self.get_connection_cloud(piece)?);
+      affected.extend(self.connections_around(piece));
+    }
+
+    // with crossing links, the links of the fallen pieces kept links of the other player
+    // from being made anywhere near them, as far out as `crosses_link_of` looks
+    if self.rules.blocking == Blocking::Crossing {
+      let distance = self.rules.links.reach() * 2;
+      for piece in &pieces_to_remove {
+        for linked in self.chains.links_of(piece) {
+          let to = piece + &self.board.delta(piece, linked);
+          let middle = Location::new((piece.x + to.x) / 2, (piece.y + to.y) / 2);
+          affected.extend(self.squares_around(&middle, distance));
+        }
+      }
+    }
+
+    self.chains.remove_chain(chain);
+    for piece in &pieces_to_remove {
+      self.board.clear_at(piece)?;
+    }
+    affected.extend(pieces_to_remove.iter().cloned());
+
+    // links of the other player that ran past the fallen pieces are no longer blocked
+    let other_player = player.switch();
+    for coordinate in &affected {
+      if self.board.is_color_at(coordinate, &other_player) {
+        for linked in self.linked_stones(coordinate)? {
+          self.chains.add_link(coordinate, &linked);
+        }
+      }
+    }
+
+    for coordinate in &affected {
+      self.update_legality(coordinate, effects);
+    }
+
+    Ok(pieces_to_remove)
+  }
+
+  /// Returns whether a square is legal for a player, looking only at the board as the
+  /// overlay changes it: it has to be empty, and an anchor or reachable from one of the
+  /// player's stones.
+  fn is_legal_for(&self, location: &Location, player: &Player, overlay: &Overlay) -> bool {
+    overlay.get_at(&self.board, location) == Ok(&BoardValue::Empty) && (
+      self.is_anchor_square(location, player) ||
+      self.get_reachable_with(location, player, overlay)
         .iter()
-        .map(|i| Location::new(i.0, i.1))
-        .map(|i| proxy.get_absolute(&i))
-        .collect()
+        .any(|i| overlay.is_color_at(&self.board, i, player))
+    )
+  }
+
+  /// Recomputes whether a square is in the legal moves sets of both players.
+  fn update_legality(&mut self, location: &Location, effects: &mut MoveEffects) {
+    for player in &[Player::Black, Player::White] {
+      let legal = self.is_legal_for(location, player, &Overlay::default());
+      self.set_legal(player, location, legal, effects);
+    }
+  }
+
+  /// Returns all the squares around a placed piece,
+  ///  that used to be accessible from other places around the placed piece,
+  /// but are now blocked off by this placed piece.
+  fn get_cut_off_squares(&self, location: &Location) -> Result<Vec<Location>, ErrorKind> {
+    self.get_cut_off_squares_with(location, &Overlay::default())
+  }
+
+  /// Like `get_cut_off_squares`, on the board as the overlay changes it.
+  fn get_cut_off_squares_with(&self, location: &Location, overlay: &Overlay) -> Result<Vec<Location>, ErrorKind> {
+    let other_player = match overlay.get_at(&self.board, location)?{
+      BoardValue::Filled(i) => i.switch(),
+      _ => return Err(ErrorKind::InvalidInput),
     };
-      
-    Ok(result_vec.iter().
-      filter(|i| 
-        i.x >= 0 && 
-        i.x < self.board.size_x as isize && 
-        i.y >= 0 &&
-        i.y < self.board.size_y as isize
-      )
+
+    let empty = BoardValue::Empty;
+
+    // with crossing links, the new links of the piece can cut off squares in any direction
+    let candidates = match self.rules.blocking {
+      Blocking::Crossing => self.squares_around(location, self.rules.links.reach() * 2),
+      _ => self.get_connection_cloud_with(location, overlay)?,
+    };
+
+    Ok(candidates
+      .iter()
+      .filter(|i| {
+        overlay.get_at(&self.board, i) == Ok(&empty) &&
+        !self.is_anchor_square(i, &other_player) &&
+        self.get_reachable_with(i, &other_player, overlay)
+          .iter()
+          .filter(|i| overlay.is_color_at(&self.board, i, &other_player))
+          .peekable() // is empty
+          .peek()
+          .is_none()
+      })
       .cloned()
       .collect())
   }
-  
 
+  /// Calculates and returns for all pieces of around a location which are the opposite
+  /// color to the piece, the locations that would be blocked by the placing of the
+  /// piece at the location. This can be used to check if pieces in these places should 
+  /// fall, or be removed from the legal moves sets.
+  fn get_connection_cloud(&self, location: &Location) -> Result<Vec<Location>, ErrorKind> {
+    self.get_connection_cloud_with(location, &Overlay::default())
+  }
+
+  /// Like `get_connection_cloud`, on the board as the overlay changes it.
+  fn get_connection_cloud_with(&self, location: &Location, overlay: &Overlay) -> Result<Vec<Location>, ErrorKind> {
+    let other_player = match overlay.get_at(&self.board, location)?{
+      BoardValue::Filled(i) => i.switch(),
+      _ => return Err(ErrorKind::InvalidInput),
+    };
+
+    if self.rules.blocking != Blocking::Occupancy {
+      return Ok(Vec::new());
+    }
+
+    let proxy = self.board.proxy(location);
+    let mut result = Vec::new();
+
+    for (from, to) in self.rules.links.passing() {
+      let from = proxy.get_absolute(&from);
+      let to = proxy.get_absolute(&to);
+      if overlay.is_color_at(&self.board, &from, &other_player) && self.board.in_bounds(&to) && !result.contains(&to) {
+        result.push(to);
+      }
+    }
+
+    Ok(result)
+  }
+  
   fn get_reachable(&self, location: &Location, player: &Player) -> Vec<Location>{
+    self.get_reachable_with(location, player, &Overlay::default())
+  }
+
+  /// Like `get_reachable`, on the board as the overlay changes it.
+  fn get_reachable_with(&self, location: &Location, player: &Player, overlay: &Overlay) -> Vec<Location>{
 
     self.connections_around(location)
       .iter()
-      .filter(|square| self.is_connection_between(location, square, player).unwrap_or(false))
+      .filter(|square| self.is_connection_between_with(location, square, player, overlay).unwrap_or(false))
       .map(|i| i.to_owned())
       .collect()
   }
 
   fn connections_around(&self, location: &Location) -> Vec<Location>{
     let proxy = self.board.proxy(location);
-    
-    let result = vec![
-      proxy.get_absolute(&Location::new(-2, 1)),
-      proxy.get_absolute(&Location::new(2, 1)),
-      proxy.get_absolute(&Location::new(2, -1)),
-      proxy.get_absolute(&Location::new(-2, -1)),
-      
-      proxy.get_absolute(&Location::new(-1, 2)),
-      proxy.get_absolute(&Location::new(1, 2)),
-      proxy.get_absolute(&Location::new(1, -2)),
-      proxy.get_absolute(&Location::new(-1, -2))
-    ];
 
-    result.iter().filter(|i| 
-      i.x >= 0 && 
-      i.x < self.board.size_x as isize && 
-      i.y >= 0 &&
-      i.y < self.board.size_y as isize
-    ).cloned().collect()
+    self.rules.links.offsets().map(|i| proxy.get_absolute(i)).filter(|i| self.board.in_bounds(i)).collect()
   }
 
   /// Calculates if one connection between two squares is uninterrupted 
@@ -336,130 +939,84 @@ impl Position{
   /// or is in the bounds of the board.
   /// Returns false when there is no piece at the from location.
   fn is_connection_between(&self, from: &Location, to: &Location, player: &Player) -> Result<bool, ErrorKind>{
+    self.is_connection_between_with(from, to, player, &Overlay::default())
+  }
+
+  /// Like `is_connection_between`, on the board as the overlay changes it.
+  fn is_connection_between_with(&self, from: &Location, to: &Location, player: &Player, overlay: &Overlay) -> Result<bool, ErrorKind>{
 
-    let delta_x = to.x - from.x;
-    let delta_y = to.y - from.y;
+    let delta = self.board.delta(from, to);
 
     let other_player = player.switch();
 
     let proxy = self.board.proxy(from);
 
-    Ok(match (delta_x, delta_y) {
-      (2, 1) => {
-        !((proxy.is_color_at(&Location::new( 0, 1), &other_player)  ||
-           proxy.is_color_at(&Location::new( 1, 1), &other_player)) &&
-          (proxy.is_color_at(&Location::new( 1, 0), &other_player)  ||
-           proxy.is_color_at(&Location::new( 2, 0), &other_player)))
-      },
-      (2, -1) => {
-        !((proxy.is_color_at(&Location::new( 0,-1), &other_player)  ||
-           proxy.is_color_at(&Location::new( 1,-1), &other_player)) &&
-          (proxy.is_color_at(&Location::new( 1, 0), &other_player)  ||
-           proxy.is_color_at(&Location::new( 2, 0), &other_player)))
-      },
-      (-2, 1) => {
-        !((proxy.is_color_at(&Location::new( 0, 1), &other_player)  ||
-           proxy.is_color_at(&Location::new(-1, 1), &other_player)) &&
-          (proxy.is_color_at(&Location::new(-1, 0), &other_player)  ||
-           proxy.is_color_at(&Location::new(-2, 0), &other_player)))
-      },
-      (-2, -1) => {
-        !((proxy.is_color_at(&Location::new( 0,-1), &other_player)  ||
-           proxy.is_color_at(&Location::new(-1,-1), &other_player)) &&
-          (proxy.is_color_at(&Location::new(-1, 0), &other_player)  ||
-           proxy.is_color_at(&Location::new(-2, 0), &other_player)))
-      },
-      (1, 2) => {
-        !((proxy.is_color_at(&Location::new( 1, 0), &other_player)  ||
-           proxy.is_color_at(&Location::new( 1, 1), &other_player)) &&
-          (proxy.is_color_at(&Location::new( 0, 1), &other_player)  ||
-           proxy.is_color_at(&Location::new( 0, 2), &other_player)))
-      },
-      (1, -2) => {
-        !((proxy.is_color_at(&Location::new( 1, 0), &other_player)  ||
-           proxy.is_color_at(&Location::new( 1,-1), &other_player)) &&
-          (proxy.is_color_at(&Location::new( 0,-1), &other_player)  ||
-           proxy.is_color_at(&Location::new( 0,-2), &other_player)))
-      },
-      (-1, 2) => {
-        !((proxy.is_color_at(&Location::new(-1, 0), &other_player)  ||
-           proxy.is_color_at(&Location::new(-1, 1), &other_player)) &&
-          (proxy.is_color_at(&Location::new( 0, 1), &other_player)  ||
-           proxy.is_color_at(&Location::new( 0, 2), &other_player)))
-      },
-      (-1, -2) => {
-        !((proxy.is_color_at(&Location::new(-1, 0), &other_player)  ||
-           proxy.is_color_at(&Location::new(-1,-1), &other_player)) &&
-          (proxy.is_color_at(&Location::new( 0,-1), &other_player)  ||
-           proxy.is_color_at(&Location::new( 0,-2), &other_player)))
-      },
-      _ => return Ok(false)
+    let shape = match self.rules.links.shape(&delta) {
+      Some(i) => i,
+      None => return Ok(false),
+    };
+
+    Ok(match self.rules.blocking {
+      Blocking::Occupancy => !shape.blockers
+        .iter()
+        .all(|set| set.iter().any(|i| overlay.blocks(&self.board, &proxy.get_absolute(i), player))),
+      Blocking::Crossing => !self.crosses_link_of(from, &(from + &delta), &other_player, overlay),
+      Blocking::Never => true,
     })
   }
-}
 
-impl Default for Position {
-  fn default() -> Self {
-    
-    let board = Board::default();
-    let mut edges = HashSet::new();
+  /// Returns whether a link between two squares would cross a link of the given player,
+  /// on the board as the overlay changes it.
+  /// On a wrapped board `to` may lie past the side, so the link is a straight line.
+  fn crosses_link_of(&self, from: &Location, to: &Location, player: &Player, overlay: &Overlay) -> bool {
+    // any link crossing this one has its stones close to the middle of it
+    let middle = Location::new((from.x + to.x) / 2, (from.y + to.y) / 2);
+    let distance = self.rules.links.reach() * 2;
+
+    for x in middle.x - distance..=middle.x + distance {
+      for y in middle.y - distance..=middle.y + distance {
+        let stone = Location::new(x, y);
+        let square = self.board.wrap(&stone);
+        if !overlay.is_color_at(&self.board, &square, player) {
+          continue;
+        }
 
-    for x in 0..board.size_x {
-      edges.insert(Location::new(x as isize, 0));
-      edges.insert(Location::new(x as isize, board.size_y as isize - 1));
+        for linked in self.chains.links_of(&square).chain(overlay.links_of(&square)) {
+          let linked = &stone + &self.board.delta(&square, linked);
+          if geometry::crosses(from, to, &stone, &linked) {
+            return true;
+          }
+        }
+      }
     }
 
-    for y in 1..board.size_y-1{
-      edges.insert(Location::new(0, y as isize));
-      edges.insert(Location::new(board.size_x as isize - 1, y as isize));
-    }
+    false
+  }
 
-    return Self{
-      board,
-      turn: Player::default(),
-      
-      legal_white_moves: edges.clone(),
-      legal_black_moves: edges,
+  /// Returns the squares on the board at most a distance away from a location along both axes.
+  fn squares_around(&self, location: &Location, distance: isize) -> Vec<Location> {
+    let mut result = Vec::new();
+    for x in location.x - distance..=location.x + distance {
+      for y in location.y - distance..=location.y + distance {
+        let square = self.board.wrap(&Location::new(x, y));
+        if self.board.in_bounds(&square) && !result.contains(&square) {
+          result.push(square);
+        }
+      }
     }
+    result
+  }
+}
+
+impl Default for Position {
+  fn default() -> Self {
+    Self::new_unchecked(Board::default(), RuleSet::default())
   }
 }
 
 impl std::fmt::Display for Position {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-      write!(f, "   ")?;
-      for i in 0..self.board.size_x{
-        write!(f, "{:2}", i)?;
-      }
-      write!(f, "\n")?;
-      for x in 0..self.board.size_x{
-        write!(f, "{:2} ", x)?;
-
-        for y in 0..self.board.size_y{
-          match &self.board.board[y][x]{        
-            BoardValue::Empty => {
-              let location = Location::new(y as isize,x as isize);
-              let black_contains = self.legal_black_moves.contains(&location);
-              let white_contains = self.legal_white_moves.contains(&location);
-              
-              match (black_contains, white_contains){
-                ( true, true) => write!(f, "\x1b[036m {}\x1b[0m", "x")?,
-                ( true,false) => write!(f, "\x1b[031m {}\x1b[0m", "x")?,
-                (false, true) => write!(f, "\x1b[033m {}\x1b[0m", "x")?,
-                _ => write!(f, "  ")?, 
-              }
-            },
-            BoardValue::Filled(i) => match i {
-              Player::White => write!(f, "\x1b[033m W\x1b[0m")?,
-              Player::Black => write!(f, "\x1b[031m B\x1b[0m")?
-            }
-          };
-        }
-        write!(f, "\n")?;
-      }
-
-      write!(f, "\n both: \x1b[036m {} \x1b[0m only black: \x1b[031m {} \x1b[0m only white: \x1b[033m {} \x1b[0m", "x", "x", "x")?;
-
-      Ok(())
+      write!(f, "{}", self.render(RenderMode::Ansi))
     }
 }
 
@@ -467,7 +1024,11 @@ impl std::fmt::Display for Position {
 mod tests{
   use crate::position::{Position, Player};
   use crate::location::Location;
-  use crate::board::BoardValue;
+  use crate::board::{Board, BoardValue, Topology};
+  use crate::render::RenderMode;
+  use crate::violation::Violation;
+  use crate::effects::{LegalChanges, MoveEffects};
+  use crate::rules::{Anchors, Blocking, Capture, RuleSet, Side};
 
   #[test]
   fn test_capture_pieces(){
@@ -482,6 +1043,415 @@ mod tests{
     assert_eq!(position.board.get_at(&Location::new(2,1)).unwrap(), &BoardValue::Empty);
   }
 
+  #[test]
+  fn test_capture_updates_legal_moves(){
+    let mut position = Position::default();
+    position.make_move(Location::new(0,0)).unwrap();
+    position.make_move(Location::new(1,0)).unwrap();
+    position.make_move(Location::new(2,1)).unwrap();
+    position.make_move(Location::new(0,1)).unwrap();
+
+    // the link from (0,0) to the captured stone's square stays blocked
+    assert!(!position.legal_white_moves.contains(&Location::new(2,1)));
+    assert!(!position.legal_black_moves.contains(&Location::new(2,1)));
+
+    // squares only the captured stone could reach are no longer legal for black
+    assert!(!position.legal_black_moves.contains(&Location::new(4,2)));
+    assert!(!position.legal_black_moves.contains(&Location::new(3,3)));
+    assert!(!position.legal_black_moves.contains(&Location::new(1,3)));
+    assert!(position.legal_black_moves.contains(&Location::new(4,0)));
+    assert!(position.group(&Location::new(2,1)).is_err());
+  }
+
+  #[test]
+  fn test_capture_chain(){
+    let mut position = Position::default();
+    position.make_move(Location::new(0,0)).unwrap();
+    position.make_move(Location::new(12,12)).unwrap();
+    position.make_move(Location::new(2,1)).unwrap();
+    position.make_move(Location::new(1,0)).unwrap();
+    position.make_move(Location::new(4,2)).unwrap();
+
+    assert_eq!(position.group(&Location::new(4,2)).unwrap().len(), 3);
+
+    // cutting the link between (0,0) and (2,1) leaves (2,1) and (4,2) without an anchor
+    position.make_move(Location::new(0,1)).unwrap();
+
+    assert_eq!(position.board.get_at(&Location::new(2,1)).unwrap(), &BoardValue::Empty);
+    assert_eq!(position.board.get_at(&Location::new(4,2)).unwrap(), &BoardValue::Empty);
+    assert_eq!(position.group(&Location::new(0,0)).unwrap().len(), 1);
+  }
+
+  #[test]
+  fn test_legal_changes(){
+    // the changes are collected while the sets are updated, so compare them to the sets
+    let mut position = Position::default();
+    for (x, y) in [(0,0), (12,12), (2,1), (1,0), (4,2), (0,1), (4,0), (11,10)] {
+      let black = position.legal_black_moves.clone();
+      let white = position.legal_white_moves.clone();
+      let outcome = position.make_move(Location::new(x, y)).unwrap();
+
+      assert_eq!(outcome.effects.black, LegalChanges::between(&black, &position.legal_black_moves));
+      assert_eq!(outcome.effects.white, LegalChanges::between(&white, &position.legal_white_moves));
+    }
+    assert_eq!(position.board.get_at(&Location::new(4,2)).unwrap(), &BoardValue::Empty);
+  }
+
+  #[test]
+  fn test_cut_off_edge_stays_legal(){
+    let mut position = Position::default();
+    position.make_move(Location::new(0,0)).unwrap();
+    position.make_move(Location::new(0,2)).unwrap();
+
+    // (1,2) blocks the only white link to (1,0), but edge squares are always legal
+    position.make_move(Location::new(1,2)).unwrap();
+    assert!(position.legal_white_moves.contains(&Location::new(1,0)));
+    assert!(position.legal_black_moves.contains(&Location::new(1,0)));
+  }
+
+  #[test]
+  fn test_simulate_move(){
+    let mut position = Position::default();
+    position.make_move(Location::new(0,0)).unwrap();
+    position.make_move(Location::new(1,0)).unwrap();
+    position.make_move(Location::new(2,1)).unwrap();
+
+    assert!(position.simulate_move(&Location::new(5,5)).is_err());
+
+    let effects = position.simulate_move(&Location::new(0,1)).unwrap();
+
+    // nothing changed on the position itself
+    assert_eq!(position.turn, Player::White);
+    assert_eq!(position.board.get_at(&Location::new(0,1)).unwrap(), &BoardValue::Empty);
+    assert_eq!(position.board.get_at(&Location::new(2,1)).unwrap(), &BoardValue::Filled(Player::Black));
+
+    assert_eq!(effects.cut_links, vec![(Location::new(0,0), Location::new(2,1))]);
+    assert_eq!(effects.captured.len(), 1);
+    assert_eq!(effects.captured_stones().collect::<Vec<_>>(), vec![&Location::new(2,1)]);
+    assert!(effects.black.removed.contains(&Location::new(0,1)));
+    assert!(effects.black.removed.contains(&Location::new(4,2)));
+    assert!(effects.white.added.contains(&Location::new(1,3)));
+    assert!(effects.white.removed.contains(&Location::new(0,1)));
+
+    // and playing the move for real does the same
+    let mut played = position.clone();
+    let outcome = played.make_move(Location::new(0,1)).unwrap();
+    assert_eq!(played.board.get_at(&Location::new(2,1)).unwrap(), &BoardValue::Empty);
+    assert_eq!(outcome.effects, effects);
+    assert_eq!(outcome.location, Location::new(0,1));
+    assert_eq!(outcome.player, Player::White);
+    assert_eq!(outcome.next_turn, Player::Black);
+    assert_eq!(outcome.to_string(), "White played 0 1, capturing 1 stone");
+
+    let quiet = position.simulate_move(&Location::new(12,12)).unwrap();
+    assert!(quiet.captured.is_empty());
+    assert!(quiet.cut_links.is_empty());
+    assert!(quiet.black.added.is_empty());
+    assert_eq!(quiet.black.removed.len(), 1);
+  }
+
+  #[test]
+  fn test_simulate_link_cut(){
+    let mut position = Position::default();
+    for (x, y) in [(0,0), (1,0), (2,1), (12,12), (4,0)] {
+      position.make_move(Location::new(x, y)).unwrap();
+    }
+
+    // (0,1) cuts the link from (0,0) to (2,1), which keeps its anchor through (4,0)
+    let effects = position.simulate_move(&Location::new(0,1)).unwrap();
+    assert_eq!(effects.cut_links, vec![(Location::new(0,0), Location::new(2,1))]);
+    assert!(effects.captured.is_empty());
+    assert_eq!(position.make_move(Location::new(0,1)).unwrap().effects, effects);
+  }
+
+  #[test]
+  fn test_simulate_every_move(){
+    // every legal move of a few games is previewed and then played on a copy
+    let boards = [
+      (Board::empty(7, 7), RuleSet::default()),
+      (Board::empty(7, 7), RuleSet { blocking: Blocking::Crossing, ..RuleSet::default() }),
+      (Board::empty(7, 7).with_topology(Topology::Cylinder), RuleSet::default()),
+    ];
+
+    for (board, rules) in boards {
+      let mut position = Position::new(board, rules).unwrap();
+      for turn in 0..24 {
+        let mut moves: Vec<Location> = position.legal_moves().iter().cloned().collect();
+        if moves.is_empty() {
+          break;
+        }
+        moves.sort_by_key(|i| (i.y, i.x));
+
+        for location in &moves {
+          let effects = position.simulate_move(location).unwrap();
+          let outcome = position.clone().make_move(location.to_owned()).unwrap();
+          assert_eq!(outcome.effects, effects, "previewing {}", location);
+        }
+        position.make_move(moves[(turn * 7) % moves.len()].to_owned()).unwrap();
+      }
+    }
+  }
+
+  #[test]
+  fn test_queries(){
+    let mut position = Position::default();
+    position.make_move(Location::new(0,0)).unwrap();
+    position.make_move(Location::new(12,12)).unwrap();
+    position.make_move(Location::new(1,2)).unwrap();
+    position.make_move(Location::new(10,11)).unwrap();
+    position.make_move(Location::new(3,3)).unwrap();
+
+    assert_eq!(position.turn, Player::White);
+    assert!(std::ptr::eq(position.legal_moves(), position.legal_moves_for(&Player::White)));
+    assert!(position.legal_moves_for(&Player::Black).contains(&Location::new(5,4)));
+    assert!(!position.legal_moves_for(&Player::White).contains(&Location::new(5,4)));
+
+    assert_eq!(position.linked_stones(&Location::new(1,2)).unwrap().len(), 2);
+    assert!(position.linked_stones(&Location::new(5,5)).is_err());
+
+    let group = position.group(&Location::new(3,3)).unwrap();
+    assert_eq!(group.len(), 3);
+    assert!(group.contains(&Location::new(0,0)));
+    assert!(!group.contains(&Location::new(12,12)));
+
+    assert!(position.is_anchored(&Location::new(3,3)).unwrap());
+    assert!(position.is_anchored(&Location::new(10,11)).unwrap());
+  }
+
+  #[test]
+  fn test_rule_set(){
+    let moves = [(0,0), (1,0), (2,1), (0,1)];
+
+    let rules = RuleSet { capture: Capture::Never, ..RuleSet::default() };
+    let mut position = Position::new(Board::empty(9, 7), rules).unwrap();
+    assert_eq!(position.legal_moves().len(), 2 * 9 + 2 * 5);
+    for (x, y) in &moves {
+      position.make_move(Location::new(*x, *y)).unwrap();
+    }
+
+    // the link is cut, but the stone that lost its anchor stays
+    assert_eq!(position.board.get_at(&Location::new(2,1)).unwrap(), &BoardValue::Filled(Player::Black));
+    assert!(!position.is_anchored(&Location::new(2,1)).unwrap());
+
+    let rules = RuleSet { blocking: Blocking::Never, ..RuleSet::default() };
+    let mut position = Position::new(Board::default(), rules).unwrap();
+    for (x, y) in &moves {
+      position.make_move(Location::new(*x, *y)).unwrap();
+    }
+
+    // nothing can come between the two black stones
+    assert_eq!(position.group(&Location::new(2,1)).unwrap().len(), 2);
+    assert!(position.legal_black_moves.contains(&Location::new(4,2)));
+  }
+
+  #[test]
+  fn test_crossing_links(){
+    let rules = RuleSet { blocking: Blocking::Crossing, ..RuleSet::default() };
+    let mut position = Position::new(Board::default(), rules).unwrap();
+    position.make_move(Location::new(0,0)).unwrap();
+    position.make_move(Location::new(1,0)).unwrap();
+    assert!(position.legal_white_moves.contains(&Location::new(2,2)));
+
+    // the black link from (0,0) is made first, so the white link across it is not
+    position.make_move(Location::new(2,1)).unwrap();
+    assert!(!position.legal_white_moves.contains(&Location::new(2,2)));
+    assert!(position.legal_black_moves.contains(&Location::new(4,2)));
+
+    position.make_move(Location::new(0,2)).unwrap();
+    assert_eq!(position.group(&Location::new(0,2)).unwrap().len(), 1);
+    assert_eq!(position.group(&Location::new(2,1)).unwrap().len(), 2);
+
+    // placing a stone on the path of a link does not cut it
+    position.make_move(Location::new(12,12)).unwrap();
+    position.make_move(Location::new(0,1)).unwrap();
+    assert!(position.is_anchored(&Location::new(2,1)).unwrap());
+    assert_eq!(position.links(&Player::Black).len(), 1);
+  }
+
+  #[test]
+  fn test_capture_with_crossing_links(){
+    // links never get cut when they can't cross, so the capture is done by hand
+    let rules = RuleSet { blocking: Blocking::Crossing, capture: Capture::Never, ..RuleSet::default() };
+    let mut position = Position::new(Board::default(), rules).unwrap();
+    for (x, y) in [(0,1), (1,0), (2,2), (3,1), (4,3)] {
+      position.make_move(Location::new(x, y)).unwrap();
+    }
+
+    // (2,3) is not a knight move away from any black stone, only across the link in the middle
+    assert!(!position.legal_white_moves.contains(&Location::new(2,3)));
+
+    let chain = position.chains.id_of(&Location::new(2,2)).unwrap();
+    assert_eq!(position.capture_piece(chain, &mut MoveEffects::default()).unwrap().len(), 3);
+    assert!(position.legal_white_moves.contains(&Location::new(2,3)));
+  }
+
+  #[test]
+  fn test_wrapped_boards(){
+    let position = Position::new(Board::default().with_topology(Topology::Cylinder), RuleSet::default()).unwrap();
+    assert_eq!(position.legal_moves().len(), 2 * 13);
+    assert!(!position.legal_moves().contains(&Location::new(0,5)));
+
+    let rules = RuleSet { anchors: Anchors::Rows(vec![0]), ..RuleSet::default() };
+    let mut position = Position::new(Board::default().with_topology(Topology::Torus), rules).unwrap();
+    assert_eq!(position.legal_moves().len(), 13);
+
+    position.make_move(Location::new(12,0)).unwrap();
+    assert!(position.legal_black_moves.contains(&Location::new(1,1)));
+    assert!(position.legal_black_moves.contains(&Location::new(11,11)));
+
+    position.make_move(Location::new(5,0)).unwrap();
+    position.make_move(Location::new(1,1)).unwrap();
+
+    let links = position.links(&Player::Black);
+    assert_eq!(links, vec![(Location::new(1,1), Location::new(12,0))]);
+    assert!(position.board.is_wrapped(&links[0].0, &links[0].1));
+    assert!(position.is_anchored(&Location::new(1,1)).unwrap());
+
+    // an axis without squares has nothing to wrap around to
+    let empty = Board::empty(0, 3).with_topology(Topology::Torus);
+    assert_eq!(empty.wrap(&Location::new(2,4)), Location::new(2,1));
+    assert_eq!(empty.delta(&Location::new(0,0), &Location::new(2,2)), Location::new(2,-1));
+  }
+
+  #[test]
+  fn test_unplayable_rules(){
+    // a knight move reaches two squares, so a wrapped side needs five
+    let rules = RuleSet { anchors: Anchors::Rows(vec![0]), ..RuleSet::default() };
+    assert!(Position::new(Board::empty(4, 9).with_topology(Topology::Cylinder), rules.clone()).is_err());
+    assert!(Position::new(Board::empty(5, 4).with_topology(Topology::Cylinder), rules.clone()).is_ok());
+    assert!(Position::new(Board::empty(9, 4).with_topology(Topology::Torus), rules.clone()).is_err());
+    assert!(Position::new(Board::empty(5, 5).with_topology(Topology::Torus), rules).is_ok());
+
+    // a torus has no edge to anchor to
+    assert!(Position::new(Board::default().with_topology(Topology::Torus), RuleSet::default()).is_err());
+    assert!(Position::new(Board::empty(0, 0), RuleSet::default()).is_err());
+
+    let mut board = Board::empty(3, 3);
+    board.set_at(&Location::new(1,1), BoardValue::Blocked).unwrap();
+    assert!(Position::new(board, RuleSet { anchors: Anchors::Centre, ..RuleSet::default() }).is_err());
+  }
+
+  #[test]
+  fn test_neutral_and_blocked(){
+    let mut board = Board::default();
+    board.set_at(&Location::new(1,0), BoardValue::Neutral).unwrap();
+    board.set_at(&Location::new(0,5), BoardValue::Blocked).unwrap();
+
+    let mut position = Position::new(board, RuleSet::default()).unwrap();
+    assert!(!position.legal_moves().contains(&Location::new(1,0)));
+    assert!(!position.legal_moves().contains(&Location::new(0,5)));
+    assert!(position.make_move(Location::new(0,5)).is_err());
+
+    // the neutral stone takes the place of the white stone in `test_capture_pieces`
+    position.make_move(Location::new(0,0)).unwrap();
+    position.make_move(Location::new(12,12)).unwrap();
+    position.make_move(Location::new(2,1)).unwrap();
+    let outcome = position.make_move(Location::new(0,1)).unwrap();
+
+    assert_eq!(outcome.captured_stones().collect::<Vec<_>>(), vec![&Location::new(2,1)]);
+    assert_eq!(position.board.get_at(&Location::new(1,0)).unwrap(), &BoardValue::Neutral);
+
+    let output = position.render(RenderMode::Ascii).to_string();
+    assert!(output.lines().nth(1).unwrap().starts_with(" 0  B N"));
+    assert!(output.lines().nth(6).unwrap().starts_with(" 5  #"));
+  }
+
+  #[test]
+  fn test_custom_anchors(){
+    let rules = RuleSet {
+      anchors: Anchors::Sides { black: vec![Side::Top, Side::Bottom], white: vec![Side::Left, Side::Right] },
+      ..RuleSet::default()
+    };
+    let mut position = Position::new(Board::default(), rules).unwrap();
+    assert_eq!(position.legal_black_moves.len(), 26);
+    assert!(position.legal_white_moves.contains(&Location::new(0,5)));
+    assert!(!position.legal_black_moves.contains(&Location::new(0,5)));
+
+    position.make_move(Location::new(5,0)).unwrap();
+    position.make_move(Location::new(0,5)).unwrap();
+    position.make_move(Location::new(6,2)).unwrap();
+
+    // black can still play on the left side when one of its stones reaches it
+    position.make_move(Location::new(12,12)).unwrap();
+    position.make_move(Location::new(4,3)).unwrap();
+    assert!(position.legal_black_moves.contains(&Location::new(2,4)));
+    assert!(!position.legal_black_moves.contains(&Location::new(0,3)));
+    assert!(position.is_anchored(&Location::new(4,3)).unwrap());
+
+    let rules = RuleSet { anchors: Anchors::Centre, ..RuleSet::default() };
+    let position = Position::new(Board::default(), rules).unwrap();
+    assert_eq!(position.legal_moves().iter().collect::<Vec<_>>(), vec![&Location::new(6,6)]);
+    let position = Position::new(Board::empty(8, 8), RuleSet { anchors: Anchors::Centre, ..RuleSet::default() }).unwrap();
+    assert_eq!(position.legal_moves().len(), 4);
+
+    let rules = RuleSet {
+      anchors: Anchors::Squares { black: vec![Location::new(3,3)], white: vec![Location::new(9,9), Location::new(9,3)] },
+      ..RuleSet::default()
+    };
+    let position = Position::new(Board::default(), rules).unwrap();
+    assert_eq!(position.legal_black_moves.len(), 1);
+    assert_eq!(position.legal_white_moves.len(), 2);
+    assert!(position.is_anchor_square(&Location::new(9,3), &Player::White));
+    assert!(!position.is_anchor_square(&Location::new(9,3), &Player::Black));
+  }
+
+  #[test]
+  fn test_swap_and_handicap(){
+    let rules = RuleSet { swap: true, ..RuleSet::default() };
+    let mut position = Position::new(Board::default(), rules.clone()).unwrap();
+    assert!(position.swap().is_err());
+
+    position.make_move(Location::new(0,0)).unwrap();
+    position.swap().unwrap();
+    assert_eq!(position.turn, Player::Black);
+    assert_eq!(position.moves_played(), 2);
+    assert_eq!(position.board.get_at(&Location::new(0,0)).unwrap(), &BoardValue::Filled(Player::White));
+    assert!(position.group(&Location::new(0,0)).is_ok());
+    assert!(position.swap().is_err());
+
+    // only when the rules allow it
+    let mut position = Position::default();
+    position.make_move(Location::new(0,0)).unwrap();
+    assert!(position.swap().is_err());
+
+    let stones = [Location::new(0,0), Location::new(12,12)];
+    let position = Position::with_handicap(Board::default(), rules.clone(), &stones).unwrap();
+    assert_eq!(position.turn, Player::White);
+    assert!(!position.legal_white_moves.contains(&Location::new(12,12)));
+    assert!(Position::with_handicap(Board::default(), rules, &[Location::new(6,6)]).is_err());
+  }
+
+  #[test]
+  fn test_validate(){
+    let mut position = Position::default();
+    position.make_move(Location::new(0,0)).unwrap();
+    position.make_move(Location::new(1,0)).unwrap();
+    assert_eq!(position.validate(), Ok(()));
+
+    // bypassing make_move leaves the stone without a chain, and its square legal
+    position.board.insert_piece(&Player::Black, &Location::new(2,1)).unwrap();
+    position.board.insert_piece(&Player::White, &Location::new(6,6)).unwrap();
+    let violations = position.validate().unwrap_err();
+    assert!(violations.contains(&Violation::MissingChain { location: Location::new(2,1) }));
+    assert!(violations.contains(&Violation::IllegalSquare { player: Player::Black, location: Location::new(2,1) }));
+    assert!(violations.contains(&Violation::Uncaptured { player: Player::White, location: Location::new(6,6) }));
+    assert_eq!(violations.len(), 5);
+
+    position.board.clear_at(&Location::new(0,0)).unwrap();
+    assert!(position.validate().unwrap_err().contains(&Violation::StaleChain { location: Location::new(0,0) }));
+
+    let mut position = Position::default();
+    position.board.append(vec![BoardValue::Empty; 12]);
+    assert_eq!(position.validate(), Err(vec![
+      Violation::Width { size_x: 13, columns: 14 },
+      Violation::Height { size_y: 13, x: 13, squares: 12 },
+    ]));
+    assert_eq!(
+      Violation::Width { size_x: 13, columns: 14 }.to_string(),
+      "the board is 13 wide but has 14 columns"
+    );
+  }
+
   #[test]
   fn test_insert_piece_error(){
     let mut position = Position::default();
@@ -735,19 +1705,3 @@ mod tests{
   }
 }
 
-
-
-// O O N O O
-// O N N N O
-// N N Z N N
-// O N N N O
-// O O N O O
-
-
-// O B A O O
-// O C B O O
-// O D W O O
-// O O O O O
-// O O O O O
-
-// connection = !((A | B) & (C | D))
\ No newline at end of file
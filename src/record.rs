@@ -0,0 +1,394 @@
+use std::io::ErrorKind;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::board::{Board, BoardValue, Topology};
+use crate::clock::{self, GameClock, Seconds, TimeControl};
+use crate::geometry::{Geometry, LinkShape};
+use crate::position::{Player, Position};
+use crate::location::Location;
+use crate::rules::{Anchors, Blocking, Capture, RuleSet, Side};
+
+/// The moves of a game in the order they were played, with the board and rules it started from.
+///
+/// As text a record is one move per line written as `x y`, or `x y seconds` when the
+/// game was timed. Before the moves come the headers, each left out when it is the default:
+/// the board as `size 9 9`, `topology cylinder` and `square x y blocked` for every square
+/// that is not empty, the rules as `anchors rows 0 8`, `blocking crossing`, `capture never`
+/// and a `link` line for every shape when the links are not knight moves, `swap` when the
+/// swap rule is on, the time control as `clock 300+5` and a handicap stone as `handicap x y`.
+/// Squares in a list are written `x,y` and the lists of both players are split by a `/`,
+/// as in `anchors squares 0,0 / 8,8`, and so are the blocker sets in `link 2 1 0,1 1,1 / 1,0 2,0`.
+/// A `swap` right after the first move means white took it over. Empty lines and lines
+/// starting with `#` are ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GameRecord {
+  /// The board before the first move and before the handicap stones.
+  pub board: Board,
+  pub rules: RuleSet,
+  /// The black stones on the board before the first move, which is then white's.
+  pub handicap: Vec<Location>,
+  pub moves: Vec<Location>,
+  /// Whether white took over the first move, see `Position::swap`.
+  pub swapped: bool,
+  pub clock: Option<TimeControl>,
+  /// The time spent on every move, empty when the game was not timed.
+  pub times: Vec<Duration>,
+}
+
+impl GameRecord {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn push(&mut self, location: Location) {
+    self.moves.push(location);
+  }
+
+  /// Adds a move with the time it took.
+  pub fn push_timed(&mut self, location: Location, elapsed: Duration) {
+    self.moves.push(location);
+    self.times.push(elapsed);
+  }
+
+  /// Replays the times of the game on its clock, which tells who lost on time if anyone did.
+  /// Returns `None` for an untimed game.
+  pub fn game_clock(&self) -> Option<GameClock> {
+    let mut clock = GameClock::new(self.clock.clone()?);
+    let mut player = if self.handicap.is_empty() { Player::Black } else { Player::White };
+
+    for (i, elapsed) in self.times.iter().enumerate() {
+      if clock.spend(&player, *elapsed).is_err() {
+        break;
+      }
+
+      player = player.switch();
+      if i == 0 && self.swapped {
+        player = player.switch();
+      }
+    }
+
+    Some(clock)
+  }
+
+  /// Returns the position the game starts from, by the rules it is played with.
+  pub fn start(&self) -> Result<Position, ErrorKind> {
+    Position::with_handicap(self.board.clone(), self.rules.clone(), &self.handicap)
+  }
+
+  /// Replays the game and returns every position in it, starting with the board before
+  /// the first move and ending with the final position. A swap is a position of its own.
+  pub fn positions(&self) -> Result<Vec<Position>, ErrorKind> {
+    let mut position = self.start()?;
+    let mut result = vec![position.clone()];
+
+    for (i, location) in self.moves.iter().enumerate() {
+      position.make_move(location.to_owned())?;
+      result.push(position.clone());
+
+      if i == 0 && self.swapped {
+        position.swap()?;
+        result.push(position.clone());
+      }
+    }
+
+    Ok(result)
+  }
+}
+
+impl std::fmt::Display for GameRecord {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let default = Board::default();
+    if (self.board.size_x, self.board.size_y) != (default.size_x, default.size_y) {
+      writeln!(f, "size {} {}", self.board.size_x, self.board.size_y)?;
+    }
+    if self.board.topology != Topology::Flat {
+      writeln!(f, "topology {}", self.board.topology.name())?;
+    }
+    for x in 0..self.board.size_x {
+      for y in 0..self.board.size_y {
+        let name = match &self.board[x][y] {
+          BoardValue::Empty => continue,
+          BoardValue::Filled(Player::Black) => "black",
+          BoardValue::Filled(Player::White) => "white",
+          BoardValue::Blocked => "blocked",
+          BoardValue::Neutral => "neutral",
+        };
+        writeln!(f, "square {} {} {}", x, y, name)?;
+      }
+    }
+
+    match &self.rules.anchors {
+      Anchors::Edge => (),
+      Anchors::Rows(rows) => {
+        let rows: Vec<String> = rows.iter().map(|i| i.to_string()).collect();
+        writeln!(f, "anchors rows {}", rows.join(" "))?;
+      },
+      Anchors::Sides { black, white } => {
+        let names = |sides: &[Side]| sides.iter().map(|i| i.name()).collect::<Vec<_>>().join(" ");
+        writeln!(f, "anchors sides {} / {}", names(black), names(white))?;
+      },
+      Anchors::Centre => writeln!(f, "anchors centre")?,
+      Anchors::Squares { black, white } => {
+        writeln!(f, "anchors squares {} / {}", write_locations(black), write_locations(white))?;
+      },
+    }
+    if self.rules.links != Geometry::knight() {
+      for shape in self.rules.links.shapes() {
+        let blockers: Vec<String> = shape.blockers.iter().map(|i| write_locations(i)).collect();
+        writeln!(f, "link {} {} {}", shape.offset.x, shape.offset.y, blockers.join(" / "))?;
+      }
+    }
+    if self.rules.blocking != Blocking::default() {
+      writeln!(f, "blocking {}", self.rules.blocking.name())?;
+    }
+    if self.rules.capture != Capture::default() {
+      writeln!(f, "capture {}", self.rules.capture.name())?;
+    }
+    if self.rules.swap {
+      writeln!(f, "swap")?;
+    }
+    if let Some(i) = &self.clock {
+      writeln!(f, "clock {}", i)?;
+    }
+
+    for location in &self.handicap {
+      writeln!(f, "handicap {} {}", location.x, location.y)?;
+    }
+
+    for (i, location) in self.moves.iter().enumerate() {
+      match self.times.get(i) {
+        Some(elapsed) => writeln!(f, "{} {} {}", location.x, location.y, Seconds(*elapsed))?,
+        None => writeln!(f, "{} {}", location.x, location.y)?,
+      }
+      if i == 0 && self.swapped {
+        writeln!(f, "swap")?;
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl FromStr for GameRecord {
+  type Err = ErrorKind;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut record = GameRecord::new();
+    // the board is built once all headers are read, since its size may come after its squares
+    let mut size = None;
+    let mut topology = None;
+    let mut squares = Vec::new();
+    let mut shapes = Vec::new();
+
+    for line in s.lines().map(str::trim) {
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+
+      let header = record.moves.is_empty();
+      let tokens: Vec<&str> = line.split_whitespace().collect();
+      match tokens.as_slice() {
+        ["swap"] if header && !record.rules.swap => {
+          record.rules.swap = true;
+        },
+        ["swap"] if record.moves.len() == 1 && record.rules.swap && !record.swapped => {
+          record.swapped = true;
+        },
+        ["handicap", x, y] if header => {
+          record.handicap.push(parse_location(x, y)?);
+        },
+        ["clock", control] if header && record.clock.is_none() => {
+          record.clock = Some(control.parse()?);
+        },
+        ["size", x, y] if header && size.is_none() => match (x.parse(), y.parse()) {
+          (Ok(x), Ok(y)) => size = Some((x, y)),
+          _ => return Err(ErrorKind::InvalidData),
+        },
+        ["topology", name] if header && topology.is_none() => {
+          topology = Some(Topology::from_name(name).ok_or(ErrorKind::InvalidData)?);
+        },
+        ["square", x, y, name] if header => {
+          let value = match *name {
+            "black" => BoardValue::Filled(Player::Black),
+            "white" => BoardValue::Filled(Player::White),
+            "blocked" => BoardValue::Blocked,
+            "neutral" => BoardValue::Neutral,
+            _ => return Err(ErrorKind::InvalidData),
+          };
+          squares.push((parse_location(x, y)?, value));
+        },
+        ["anchors", kind, lists @ ..] if header && record.rules.anchors == Anchors::default() => {
+          record.rules.anchors = parse_anchors(kind, lists)?;
+        },
+        ["link", x, y, blockers @ ..] if header => {
+          let blockers = match blockers {
+            [] => Vec::new(),
+            _ => blockers.split(|i| *i == "/").map(parse_locations).collect::<Result<_, _>>()?,
+          };
+          shapes.push(LinkShape { offset: parse_location(x, y)?, blockers });
+        },
+        ["blocking", name] if header && record.rules.blocking == Blocking::default() => {
+          record.rules.blocking = Blocking::from_name(name).ok_or(ErrorKind::InvalidData)?;
+        },
+        ["capture", name] if header && record.rules.capture == Capture::default() => {
+          record.rules.capture = Capture::from_name(name).ok_or(ErrorKind::InvalidData)?;
+        },
+        [x, y] => record.push(parse_location(x, y)?),
+        [x, y, elapsed] => {
+          record.push_timed(parse_location(x, y)?, clock::parse_seconds(elapsed)?);
+        },
+        _ => return Err(ErrorKind::InvalidData),
+      }
+    }
+
+    // either every move has its time or none has
+    if !record.times.is_empty() && record.times.len() != record.moves.len() {
+      return Err(ErrorKind::InvalidData);
+    }
+
+    if let Some((x, y)) = size {
+      record.board = Board::empty(x, y);
+    }
+    record.board = record.board.with_topology(topology.unwrap_or_default());
+    for (location, value) in squares {
+      record.board.set_at(&location, value).map_err(|_| ErrorKind::InvalidData)?;
+    }
+    if !shapes.is_empty() {
+      record.rules.links = Geometry::new(shapes);
+    }
+
+    Ok(record)
+  }
+}
+
+fn parse_location(x: &str, y: &str) -> Result<Location, ErrorKind> {
+  match (x.parse(), y.parse()) {
+    (Ok(x), Ok(y)) => Ok(Location::new(x, y)),
+    _ => Err(ErrorKind::InvalidData),
+  }
+}
+
+/// Parses squares written as `x,y`.
+fn parse_locations(list: &[&str]) -> Result<Vec<Location>, ErrorKind> {
+  list.iter()
+    .map(|i| i.split_once(',').ok_or(ErrorKind::InvalidData).and_then(|(x, y)| parse_location(x, y)))
+    .collect()
+}
+
+fn write_locations(list: &[Location]) -> String {
+  list.iter().map(|i| format!("{},{}", i.x, i.y)).collect::<Vec<_>>().join(" ")
+}
+
+/// Parses what follows `anchors`, which for two players are two lists split by a `/`.
+fn parse_anchors(kind: &str, lists: &[&str]) -> Result<Anchors, ErrorKind> {
+  let mut players = lists.split(|i| *i == "/");
+  let sides = |list: &[&str]| list.iter().map(|i| Side::from_name(i)).collect::<Option<Vec<_>>>();
+
+  let anchors = match (kind, players.next(), players.next(), players.next()) {
+    ("edge", Some([]), None, None) => Some(Anchors::Edge),
+    ("centre", Some([]), None, None) => Some(Anchors::Centre),
+    ("rows", Some(rows), None, None) => rows.iter().map(|i| i.parse().ok()).collect::<Option<_>>().map(Anchors::Rows),
+    ("sides", Some(black), Some(white), None) => match (sides(black), sides(white)) {
+      (Some(black), Some(white)) => Some(Anchors::Sides { black, white }),
+      _ => None,
+    },
+    ("squares", Some(black), Some(white), None) => Some(Anchors::Squares {
+      black: parse_locations(black)?,
+      white: parse_locations(white)?,
+    }),
+    _ => None,
+  };
+
+  anchors.ok_or(ErrorKind::InvalidData)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::record::GameRecord;
+  use crate::location::Location;
+  use crate::board::BoardValue;
+  use crate::geometry::{Geometry, LinkShape};
+  use crate::rules::{Anchors, RuleSet, Side};
+
+  #[test]
+  fn test_record_round_trip() {
+    let record: GameRecord = "# a short game\n0 0\n1 0\n\n2 1\n0 1\n".parse().unwrap();
+
+    assert_eq!(record.moves.len(), 4);
+    assert_eq!(record.to_string().parse::<GameRecord>().unwrap(), record);
+    assert!("0 0 0 0".parse::<GameRecord>().is_err());
+    assert!("a b".parse::<GameRecord>().is_err());
+
+    let positions = record.positions().unwrap();
+    assert_eq!(positions.len(), 5);
+    assert_eq!(positions[3].board.get_at(&Location::new(2, 1)).unwrap(), &BoardValue::Filled(crate::position::Player::Black));
+    assert_eq!(positions[4].board.get_at(&Location::new(2, 1)).unwrap(), &BoardValue::Empty);
+  }
+
+  #[test]
+  fn test_record_swap_and_handicap() {
+    let record: GameRecord = "swap\n0 0\nswap\n1 0\n".parse().unwrap();
+    assert!(record.rules.swap && record.swapped);
+    assert_eq!(record.to_string().parse::<GameRecord>().unwrap(), record);
+
+    let positions = record.positions().unwrap();
+    assert_eq!(positions.len(), 4);
+    assert_eq!(positions[2].board.get_at(&Location::new(0, 0)).unwrap(), &BoardValue::Filled(crate::position::Player::White));
+
+    let record: GameRecord = "handicap 0 0\nhandicap 12 12\n1 0\n".parse().unwrap();
+    assert_eq!(record.handicap.len(), 2);
+    assert_eq!(record.to_string().parse::<GameRecord>().unwrap(), record);
+    assert_eq!(record.positions().unwrap()[0].turn, crate::position::Player::White);
+
+    // the rule can be on without white making use of it, but not the other way around
+    let record: GameRecord = "swap\n0 0\n1 0\n".parse().unwrap();
+    assert!(record.rules.swap && !record.swapped);
+    assert!(record.start().unwrap().rules().swap);
+    assert_eq!(record.to_string(), "swap\n0 0\n1 0\n");
+    assert!("0 0\nswap".parse::<GameRecord>().is_err());
+    assert!("swap\nswap\n0 0".parse::<GameRecord>().is_err());
+    assert!("0 0\nhandicap 1 1".parse::<GameRecord>().is_err());
+    assert!("handicap 6 6".parse::<GameRecord>().unwrap().positions().is_err());
+  }
+
+  #[test]
+  fn test_record_clock() {
+    let record: GameRecord = "clock 10+2x5\n0 0 3.5\n1 0 11\n2 1 12\n0 1 30\n".parse().unwrap();
+    assert_eq!(record.times.len(), 4);
+    assert_eq!(record.to_string().parse::<GameRecord>().unwrap(), record);
+    assert_eq!(record.game_clock().unwrap().flagged(), Some(&crate::position::Player::White));
+
+    assert!("clock 10\n0 0 1\n1 0".parse::<GameRecord>().is_err());
+    assert!("0 0 x".parse::<GameRecord>().is_err());
+    assert!("0 0".parse::<GameRecord>().unwrap().game_clock().is_none());
+  }
+
+  #[test]
+  fn test_record_board_and_rules() {
+    let text = "size 9 9\ntopology cylinder\nsquare 4 4 blocked\nsquare 2 0 neutral\n\
+      anchors sides top / left right\nblocking crossing\ncapture never\n0 0\n";
+    let record: GameRecord = text.parse().unwrap();
+    assert_eq!(record.board.size_x, 9);
+    assert_eq!(record.board.get_at(&Location::new(4, 4)).unwrap(), &BoardValue::Blocked);
+    assert_eq!(record.rules.anchors, Anchors::Sides { black: vec![Side::Top], white: vec![Side::Left, Side::Right] });
+    assert_eq!(record.to_string().parse::<GameRecord>().unwrap(), record);
+    assert_eq!(record.positions().unwrap()[1].rules(), &record.rules);
+
+    let record = GameRecord {
+      rules: RuleSet {
+        anchors: Anchors::Squares { black: vec![Location::new(0, 0)], white: vec![Location::new(12, 12)] },
+        links: Geometry::symmetric(LinkShape::new((1, 1), &[])),
+        ..RuleSet::default()
+      },
+      ..GameRecord::new()
+    };
+    assert_eq!(record.to_string().parse::<GameRecord>().unwrap(), record);
+    assert!(record.to_string().starts_with("anchors squares 0,0 / 12,12\nlink 1 1 \n"));
+
+    assert!("anchors rows".parse::<GameRecord>().unwrap().start().is_err());
+    assert!("anchors sides top".parse::<GameRecord>().is_err());
+    assert!("square 13 0 blocked".parse::<GameRecord>().is_err());
+    assert!("0 0\nsize 9 9".parse::<GameRecord>().is_err());
+  }
+}
@@ -0,0 +1,432 @@
+use std::io::IsTerminal;
+
+use crate::position::{Position, Player};
+use crate::board::BoardValue;
+use crate::location::Location;
+use crate::effects::MoveOutcome;
+
+/// The different ways a position can be drawn as text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderMode {
+  /// Coloured output using ANSI escape codes.
+  Ansi,
+  /// Plain ASCII, legal squares are told apart by glyph instead of colour.
+  Ascii,
+  /// Unicode box drawing with distinct symbols, no colour.
+  Unicode,
+}
+
+impl RenderMode {
+  /// Uses colour only when stdout is a terminal and `NO_COLOR` is not set.
+  pub fn detect() -> Self {
+    if std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none() {
+      RenderMode::Ansi
+    } else {
+      RenderMode::Ascii
+    }
+  }
+
+  /// Parses a mode name as given on the command line.
+  /// `auto` resolves to whatever `detect` picks.
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "ansi" | "color" | "colour" => Some(RenderMode::Ansi),
+      "ascii" | "plain" => Some(RenderMode::Ascii),
+      "unicode" => Some(RenderMode::Unicode),
+      "auto" => Some(RenderMode::detect()),
+      _ => None
+    }
+  }
+
+  /// Whether this mode writes escape codes to the output.
+  pub fn uses_color(self) -> bool {
+    self == RenderMode::Ansi
+  }
+}
+
+/// What is drawn on a single square of the board.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Square {
+  Stone(bool),
+  Legal { black: bool, white: bool },
+  Neutral,
+  Hole,
+}
+
+/// Displays a position in the given render mode.
+pub struct Renderer<'p> {
+  position: &'p Position,
+  mode: RenderMode,
+  highlight: Option<&'p MoveOutcome>,
+}
+
+impl<'p> Renderer<'p> {
+  pub fn new(position: &'p Position, mode: RenderMode) -> Self {
+    Self {
+      position,
+      mode,
+      highlight: None
+    }
+  }
+
+  /// Marks what a move changed: the placed stone gets a `>` in front of it
+  /// and the squares of captured stones a `*`.
+  pub fn highlight(mut self, outcome: &'p MoveOutcome) -> Self {
+    self.highlight = Some(outcome);
+    self
+  }
+
+  /// Returns the cell for the square at a location, with the highlight applied.
+  fn highlighted_cell(&self, location: &Location) -> String {
+    let cell = self.cell(self.square_at(location));
+
+    match self.highlight {
+      Some(i) if i.location == *location => cell.replacen(' ', ">", 1),
+      Some(i) if i.captured_stones().any(|j| j == location) => cell.replacen(' ', "*", 1),
+      _ => cell.to_owned(),
+    }
+  }
+
+  fn square_at(&self, location: &Location) -> Square {
+    match &self.position.board.board[location.x as usize][location.y as usize] {
+      BoardValue::Filled(i) => Square::Stone(*i == Player::Black),
+      BoardValue::Neutral => Square::Neutral,
+      BoardValue::Blocked => Square::Hole,
+      BoardValue::Empty => Square::Legal {
+        black: self.position.legal_black_moves.contains(location),
+        white: self.position.legal_white_moves.contains(location),
+      }
+    }
+  }
+
+  /// Returns the two character wide cell for a square.
+  fn cell(&self, square: Square) -> &'static str {
+    match (self.mode, square) {
+      (RenderMode::Ansi, Square::Stone(true)) => "\x1b[031m B\x1b[0m",
+      (RenderMode::Ansi, Square::Stone(false)) => "\x1b[033m W\x1b[0m",
+      (RenderMode::Ansi, Square::Legal { black: true, white: true }) => "\x1b[036m x\x1b[0m",
+      (RenderMode::Ansi, Square::Legal { black: true, white: false }) => "\x1b[031m x\x1b[0m",
+      (RenderMode::Ansi, Square::Legal { black: false, white: true }) => "\x1b[033m x\x1b[0m",
+      (RenderMode::Ansi, Square::Legal { .. }) => "  ",
+      (RenderMode::Ansi, Square::Neutral) => "\x1b[035m N\x1b[0m",
+      (RenderMode::Ansi, Square::Hole) => "\x1b[090m #\x1b[0m",
+
+      (RenderMode::Ascii, Square::Stone(true)) => " B",
+      (RenderMode::Ascii, Square::Stone(false)) => " W",
+      (RenderMode::Ascii, Square::Legal { black: true, white: true }) => " +",
+      (RenderMode::Ascii, Square::Legal { black: true, white: false }) => " b",
+      (RenderMode::Ascii, Square::Legal { black: false, white: true }) => " w",
+      (RenderMode::Ascii, Square::Legal { .. }) => " .",
+      (RenderMode::Ascii, Square::Neutral) => " N",
+      (RenderMode::Ascii, Square::Hole) => " #",
+
+      (RenderMode::Unicode, Square::Stone(true)) => " ●",
+      (RenderMode::Unicode, Square::Stone(false)) => " ○",
+      (RenderMode::Unicode, Square::Legal { black: true, white: true }) => " ◇",
+      (RenderMode::Unicode, Square::Legal { black: true, white: false }) => " ▲",
+      (RenderMode::Unicode, Square::Legal { black: false, white: true }) => " △",
+      (RenderMode::Unicode, Square::Legal { .. }) => " ·",
+      (RenderMode::Unicode, Square::Neutral) => " ◆",
+      (RenderMode::Unicode, Square::Hole) => "  ",
+    }
+  }
+
+  fn legend(&self) -> String {
+    match self.mode {
+      RenderMode::Ansi => format!(
+        " both: {} only black: {} only white: {}",
+        self.cell(Square::Legal { black: true, white: true }),
+        self.cell(Square::Legal { black: true, white: false }),
+        self.cell(Square::Legal { black: false, white: true }),
+      ),
+      _ => format!(
+        " stones: black{} white{}  legal: both{} only black{} only white{}",
+        self.cell(Square::Stone(true)),
+        self.cell(Square::Stone(false)),
+        self.cell(Square::Legal { black: true, white: true }),
+        self.cell(Square::Legal { black: true, white: false }),
+        self.cell(Square::Legal { black: false, white: true }),
+      )
+    }
+  }
+}
+
+impl<'p> std::fmt::Display for Renderer<'p> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let board = &self.position.board;
+    let boxed = self.mode == RenderMode::Unicode;
+    let horizontal = "─".repeat(board.size_x * 2 + 1);
+
+    write!(f, "   ")?;
+    if boxed {
+      write!(f, " ")?;
+    }
+    for i in 0..board.size_x {
+      write!(f, "{:2}", i)?;
+    }
+    writeln!(f)?;
+
+    if boxed {
+      writeln!(f, "   ┌{}┐", horizontal)?;
+    }
+
+    for y in 0..board.size_y {
+      write!(f, "{:2} ", y)?;
+      if boxed {
+        write!(f, "│")?;
+      }
+
+      for x in 0..board.size_x {
+        write!(f, "{}", self.highlighted_cell(&Location::new(x as isize, y as isize)))?;
+      }
+
+      if boxed {
+        write!(f, " │")?;
+      }
+      writeln!(f)?;
+    }
+
+    if boxed {
+      writeln!(f, "   └{}┘", horizontal)?;
+    }
+
+    writeln!(f)?;
+    write!(f, "{}", self.legend())
+  }
+}
+
+/// Horizontal distance between two squares on the expanded link grid.
+const LINK_SPACING_X: isize = 4;
+/// Vertical distance between two squares on the expanded link grid.
+const LINK_SPACING_Y: isize = 2;
+
+/// A single character on the expanded link grid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mark {
+  Blank,
+  Square(Square),
+  Line(char, bool),
+  Crossing,
+}
+
+/// Displays a position on an expanded grid, with the links between
+/// stones of the same colour drawn in between the squares.
+pub struct LinkRenderer<'p> {
+  renderer: Renderer<'p>,
+}
+
+impl<'p> LinkRenderer<'p> {
+  pub fn new(position: &'p Position, mode: RenderMode) -> Self {
+    Self {
+      renderer: Renderer::new(position, mode)
+    }
+  }
+
+  /// Picks the character for a line segment going in the direction (dx, dy).
+  fn line_char(&self, dx: isize, dy: isize) -> char {
+    let unicode = self.renderer.mode == RenderMode::Unicode;
+
+    if dx.abs() > dy.abs() * 2 {
+      if unicode { '─' } else { '-' }
+    } else if dy.abs() > dx.abs() * 2 {
+      if unicode { '│' } else { '|' }
+    } else if (dx > 0) == (dy > 0) {
+      if unicode { '╲' } else { '\\' }
+    } else if unicode { '╱' } else { '/' }
+  }
+
+  /// Returns the room left around the squares along x and y, which is half the
+  /// spacing on wrapped axes, for the halves of links leaving through that side.
+  fn padding(&self) -> (isize, isize) {
+    let topology = self.renderer.position.board.topology;
+    (
+      if topology.wraps_x() { LINK_SPACING_X / 2 } else { 0 },
+      if topology.wraps_y() { LINK_SPACING_Y / 2 } else { 0 },
+    )
+  }
+
+  /// Builds the expanded grid with all squares and links on it.
+  fn canvas(&self) -> Vec<Vec<Mark>> {
+    let position = self.renderer.position;
+    let (pad_x, pad_y) = self.padding();
+    let width = (position.board.size_x as isize - 1) * LINK_SPACING_X + 1 + pad_x * 2;
+    let height = (position.board.size_y as isize - 1) * LINK_SPACING_Y + 1 + pad_y * 2;
+
+    let mut canvas = vec![vec![Mark::Blank; width.max(0) as usize]; height.max(0) as usize];
+
+    for (player, black) in &[(Player::Black, true), (Player::White, false)] {
+      for (from, to) in position.links(player) {
+        let delta = position.board.delta(&from, &to);
+        let line = Mark::Line(self.line_char(delta.x * LINK_SPACING_X, delta.y * LINK_SPACING_Y), *black);
+
+        for ((x1, y1), (x2, y2)) in position.board.link_segments(&from, &to) {
+          let canvas_point = |x: f64, y: f64| {
+            ((x * LINK_SPACING_X as f64) + pad_x as f64, (y * LINK_SPACING_Y as f64) + pad_y as f64)
+          };
+          let ((x1, y1), (x2, y2)) = (canvas_point(x1, y1), canvas_point(x2, y2));
+          let (dx, dy) = (x2 - x1, y2 - y1);
+          let steps = dx.abs().max(dy.abs()).round() as isize;
+
+          // the square the link ends on is drawn over later, half links stop at the side
+          for i in 1..=steps {
+            let x = (x1 + (dx * i as f64 / steps as f64).trunc()) as isize;
+            let y = (y1 + (dy * i as f64 / steps as f64).round()) as isize;
+            if x < 0 || y < 0 || x >= width || y >= height {
+              continue;
+            }
+            let mark = &mut canvas[y as usize][x as usize];
+
+            *mark = match *mark {
+              Mark::Blank => line,
+              Mark::Line(..) if *mark == line => line,
+              _ => Mark::Crossing,
+            };
+          }
+        }
+      }
+    }
+
+    for x in 0..position.board.size_x as isize {
+      for y in 0..position.board.size_y as isize {
+        let square = self.renderer.square_at(&Location::new(x, y));
+        canvas[(y * LINK_SPACING_Y + pad_y) as usize][(x * LINK_SPACING_X + pad_x) as usize] = Mark::Square(square);
+      }
+    }
+
+    canvas
+  }
+
+  fn mark_glyph(&self, mark: Mark) -> String {
+    let mode = self.renderer.mode;
+
+    match mark {
+      Mark::Blank => " ".to_owned(),
+      Mark::Square(i) => self.renderer.cell(i).replacen(' ', "", 1),
+      Mark::Line(c, black) if mode.uses_color() => {
+        format!("{}{}\x1b[0m", if black { "\x1b[031m" } else { "\x1b[033m" }, c)
+      },
+      Mark::Line(c, _) => c.to_string(),
+      Mark::Crossing => if mode == RenderMode::Unicode { "╳" } else { "X" }.to_owned(),
+    }
+  }
+}
+
+impl<'p> std::fmt::Display for LinkRenderer<'p> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let size_x = self.renderer.position.board.size_x;
+    let (pad_x, pad_y) = self.padding();
+
+    write!(f, "   {}", " ".repeat(pad_x as usize))?;
+    for i in 0..size_x {
+      write!(f, "{:<w$}", i, w = LINK_SPACING_X as usize)?;
+    }
+    writeln!(f)?;
+
+    for (y, row) in self.canvas().into_iter().enumerate() {
+      let y = y as isize - pad_y;
+      let mut line = if y >= 0 && y % LINK_SPACING_Y == 0 {
+        format!("{:2} ", y / LINK_SPACING_Y)
+      } else {
+        "   ".to_owned()
+      };
+
+      line.extend(row.into_iter().map(|i| self.mark_glyph(i)));
+      writeln!(f, "{}", line.trim_end())?;
+    }
+
+    writeln!(f)?;
+    write!(f, "{}", self.renderer.legend())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::board::{Board, Topology};
+  use crate::position::Position;
+  use crate::location::Location;
+  use crate::render::RenderMode;
+  use crate::rules::{Anchors, RuleSet};
+
+  #[test]
+  fn test_plain_modes_have_no_escapes() {
+    let mut position = Position::default();
+    position.make_move(Location::new(0, 0)).unwrap();
+    position.make_move(Location::new(1, 0)).unwrap();
+
+    for mode in &[RenderMode::Ascii, RenderMode::Unicode] {
+      let output = position.render(*mode).to_string();
+      assert!(!output.contains('\x1b'));
+    }
+
+    assert!(position.render(RenderMode::Ansi).to_string().contains('\x1b'));
+  }
+
+  #[test]
+  fn test_link_lines() {
+    let mut position = Position::default();
+    position.make_move(Location::new(0, 0)).unwrap();
+    position.make_move(Location::new(12, 12)).unwrap();
+    position.make_move(Location::new(1, 2)).unwrap();
+
+    let output = position.render_links(RenderMode::Ascii).to_string();
+    let rows: Vec<&str> = output.lines().collect();
+
+    assert_eq!(rows[1].trim_end(), " 0 B   +   +   +   +   +   +   +   +   +   +   +   +");
+    assert_eq!(rows[2], "    \\");
+    assert!(rows[3].starts_with(" 1 + \\"));
+    assert!(rows[4].starts_with("      \\"));
+    assert!(rows[5].starts_with(" 2 +   B"));
+  }
+
+  #[test]
+  fn test_wrapped_link_lines() {
+    let board = Board::default().with_topology(Topology::Cylinder);
+    let rules = RuleSet { anchors: Anchors::Rows(vec![0]), ..RuleSet::default() };
+    let mut position = Position::new(board, rules).unwrap();
+    position.make_move(Location::new(12, 0)).unwrap();
+    position.make_move(Location::new(5, 0)).unwrap();
+    position.make_move(Location::new(1, 1)).unwrap();
+
+    // the link leaves on the right of (12, 0) and comes back in on the left towards (1, 1)
+    let output = position.render_links(RenderMode::Ascii).to_string();
+    let rows: Vec<&str> = output.lines().collect();
+
+    assert!(rows[0].starts_with("     0   1"));
+    assert!(rows[1].starts_with(" 0 - +   +"));
+    assert!(rows[1].ends_with("B-"));
+    assert!(rows[2].starts_with("    ----"));
+    assert!(rows[2].ends_with('-'));
+    assert!(rows[3].starts_with(" 1   .  -B"));
+  }
+
+  #[test]
+  fn test_highlight() {
+    let mut position = Position::default();
+    position.make_move(Location::new(0, 0)).unwrap();
+    position.make_move(Location::new(1, 0)).unwrap();
+    position.make_move(Location::new(2, 1)).unwrap();
+    let outcome = position.make_move(Location::new(0, 1)).unwrap();
+
+    let output = position.render(RenderMode::Ascii).highlight(&outcome).to_string();
+    let rows: Vec<&str> = output.lines().collect();
+
+    assert!(rows[2].starts_with(" 1 >W .*."));
+  }
+
+  #[test]
+  fn test_ascii_glyphs() {
+    let mut position = Position::default();
+    position.make_move(Location::new(0, 0)).unwrap();
+    position.make_move(Location::new(12, 12)).unwrap();
+
+    let output = position.render(RenderMode::Ascii).to_string();
+    let rows: Vec<&str> = output.lines().collect();
+
+    // row 0: black stone, then an edge square legal for both
+    assert!(rows[1].starts_with(" 0  B +"));
+    // row 1: edge square legal for both, nothing, then a square only black reaches
+    assert!(rows[2].starts_with(" 1  + . b"));
+    // row 11: a square only white reaches, next to the edge
+    assert!(rows[12].ends_with(" w . +"));
+  }
+}
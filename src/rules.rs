@@ -0,0 +1,160 @@
+use crate::board::Board;
+use crate::geometry::Geometry;
+use crate::location::Location;
+use crate::position::Player;
+
+/// One of the four sides of the board, `Top` being row 0 and `Left` column 0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+  Top,
+  Bottom,
+  Left,
+  Right,
+}
+
+impl Side {
+  /// Parses a side as written in a game record.
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "top" => Some(Side::Top),
+      "bottom" => Some(Side::Bottom),
+      "left" => Some(Side::Left),
+      "right" => Some(Side::Right),
+      _ => None
+    }
+  }
+
+  /// The name `from_name` parses.
+  pub fn name(self) -> &'static str {
+    match self {
+      Side::Top => "top",
+      Side::Bottom => "bottom",
+      Side::Left => "left",
+      Side::Right => "right",
+    }
+  }
+
+  pub fn contains(self, board: &Board, location: &Location) -> bool {
+    board.in_bounds(location) && match self {
+      Side::Top => location.y == 0,
+      Side::Bottom => location.y == board.size_y as isize - 1,
+      Side::Left => location.x == 0,
+      Side::Right => location.x == board.size_x as isize - 1,
+    }
+  }
+}
+
+/// The squares that anchor a chain of a player. They are legal for that player
+/// from the start, and a chain with a stone on one of them is never captured.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Anchors {
+  /// The outermost ring of the board, which is only the sides that do not wrap around.
+  #[default]
+  Edge,
+  /// Whole rows of the board, for boards without edges.
+  Rows(Vec<isize>),
+  /// Sides of the board, which can be different for both players.
+  Sides { black: Vec<Side>, white: Vec<Side> },
+  /// The middle square of the board, or the middle two or four when a size is even.
+  Centre,
+  /// Any squares, which can be different for both players.
+  Squares { black: Vec<Location>, white: Vec<Location> },
+}
+
+impl Anchors {
+  pub fn contains(&self, board: &Board, location: &Location, player: &Player) -> bool {
+    match self {
+      Anchors::Edge => board.is_edge(location),
+      Anchors::Rows(rows) => board.in_bounds(location) && rows.contains(&location.y),
+      Anchors::Sides { black, white } => match player {
+        Player::Black => black,
+        Player::White => white,
+      }.iter().any(|i| i.contains(board, location)),
+      Anchors::Centre => {
+        let middle = |i: isize, size: usize| i == (size as isize - 1) / 2 || i == size as isize / 2;
+        board.in_bounds(location) && middle(location.x, board.size_x) && middle(location.y, board.size_y)
+      },
+      Anchors::Squares { black, white } => match player {
+        Player::Black => black,
+        Player::White => white,
+      }.contains(location),
+    }
+  }
+}
+
+/// When a link is interrupted by stones of the opponent.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Blocking {
+  /// The opponent occupies a square in every blocker set of the link, see `LinkShape`.
+  #[default]
+  Occupancy,
+  /// A link may not cross a link of the opponent, as in TwixT. Links are made when a
+  /// stone is placed and stay until one of their stones is captured, so a later stone
+  /// never cuts an existing link, it can only keep new links from being made.
+  Crossing,
+  /// Links are never interrupted.
+  Never,
+}
+
+impl Blocking {
+  /// Parses a blocking rule as given on the command line.
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "occupancy" => Some(Blocking::Occupancy),
+      "crossing" => Some(Blocking::Crossing),
+      "never" => Some(Blocking::Never),
+      _ => None
+    }
+  }
+
+  /// The name `from_name` parses.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Blocking::Occupancy => "occupancy",
+      Blocking::Crossing => "crossing",
+      Blocking::Never => "never",
+    }
+  }
+}
+
+/// When stones are taken off the board.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Capture {
+  /// A chain is captured when it is no longer linked to an anchor.
+  #[default]
+  Unanchored,
+  /// Stones stay on the board for the rest of the game.
+  Never,
+}
+
+impl Capture {
+  /// Parses a capture rule as given on the command line.
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "unanchored" => Some(Capture::Unanchored),
+      "never" => Some(Capture::Never),
+      _ => None
+    }
+  }
+
+  /// The name `from_name` parses.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Capture::Unanchored => "unanchored",
+      Capture::Never => "never",
+    }
+  }
+}
+
+/// The rules a `Position` is played by. The default is the standard game.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RuleSet {
+  pub anchors: Anchors,
+  /// The shapes of the links between stones, and the squares that block them.
+  pub links: Geometry,
+  pub blocking: Blocking,
+  pub capture: Capture,
+  /// Whether white may take over the first move of black instead of answering it,
+  /// see `Position::swap`.
+  pub swap: bool,
+}
@@ -0,0 +1,200 @@
+use std::io::{ErrorKind, Write};
+
+use crate::board::BoardValue;
+use crate::engine::Engine;
+use crate::location::Location;
+use crate::log::Level;
+use crate::position::{Position, Player};
+use crate::stress::Rng;
+
+/// How far apart the scores of two moves are for one to be played e times as often,
+/// see `policy`.
+const TEMPERATURE: f32 = 0.25;
+
+/// One training sample: a position, the search's distribution over the legal moves
+/// and how the game ended for the player to move.
+///
+/// Written as one line of JSON, for example
+///
+/// ```text
+/// {"size":[13,13],"board":"B.W..","turn":"white","policy":[[0,1,0.5],[2,0,0.5]],"result":-1}
+/// ```
+///
+/// `board` holds the squares row by row with the glyphs of `Diagram` (`B`, `W`, `N`,
+/// `#` and `.`), `policy` is a list of `[x, y, probability]` made from the scores of the
+/// search, see `policy`, and `result` is 1 for a win, -1 for a loss and 0 for a draw.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+  pub size_x: usize,
+  pub size_y: usize,
+  pub board: String,
+  pub turn: Player,
+  pub policy: Vec<(Location, f32)>,
+  pub result: i8,
+}
+
+impl std::fmt::Display for Sample {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let turn = match self.turn {
+      Player::Black => "black",
+      Player::White => "white",
+    };
+    let policy: Vec<String> = self.policy.iter().map(|(i, p)| format!("[{},{},{}]", i.x, i.y, p)).collect();
+
+    write!(
+      f, "{{\"size\":[{},{}],\"board\":\"{}\",\"turn\":\"{}\",\"policy\":[{}],\"result\":{}}}",
+      self.size_x, self.size_y, self.board, turn, policy.join(","), self.result
+    )
+  }
+}
+
+/// Writes the squares of a board row by row, one glyph each.
+pub fn encode_board(position: &Position) -> String {
+  let mut result = String::new();
+
+  for y in 0..position.board.size_y {
+    for x in 0..position.board.size_x {
+      result.push(match position.board.get_at(&Location::new(x as isize, y as isize)) {
+        Ok(BoardValue::Filled(Player::Black)) => 'B',
+        Ok(BoardValue::Filled(Player::White)) => 'W',
+        Ok(BoardValue::Neutral) => 'N',
+        Ok(BoardValue::Blocked) => '#',
+        _ => '.',
+      });
+    }
+  }
+
+  result
+}
+
+/// Turns the scores of a search into how likely every move is to be played, with a
+/// softmax over the scores divided by `TEMPERATURE`. Sorted so the order does not change
+/// from run to run.
+pub fn policy(scores: &[(Location, f32)]) -> Vec<(Location, f32)> {
+  let max = scores.iter().map(|i| i.1).fold(f32::NEG_INFINITY, f32::max);
+  let exps: Vec<f32> = scores.iter().map(|i| ((i.1 - max) / TEMPERATURE).exp()).collect();
+  let sum: f32 = exps.iter().sum();
+
+  let mut policy: Vec<(Location, f32)> = scores.iter().zip(exps).map(|(i, e)| (i.0.to_owned(), e / sum)).collect();
+  policy.sort_by_key(|(i, _)| (i.x, i.y));
+  policy
+}
+
+/// Picks a move with the probability the policy gives it.
+fn sample_move(policy: &[(Location, f32)], rng: &mut Rng) -> Option<Location> {
+  let mut left = (rng.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+
+  for (location, p) in policy {
+    if left < *p {
+      return Some(location.to_owned());
+    }
+    left -= p;
+  }
+
+  // rounding can leave a little over at the end
+  policy.last().map(|(i, _)| i.to_owned())
+}
+
+/// Plays one game from a position and returns a sample for every move. Every move is
+/// searched by the engine and picked by the policy made from its scores, so a game
+/// still explores moves that score a little worse.
+///
+/// The rules do not name a winner, so a player who has no legal move loses,
+/// and a game still going after `max_moves` is a draw.
+/// Fails when the search does, or when it comes up with a move that is not accepted.
+pub fn play_game(start: &Position, engine: &Engine, rng: &mut Rng, max_moves: usize) -> Result<Vec<Sample>, ErrorKind> {
+  let mut position = start.clone();
+  let mut samples = Vec::new();
+  let mut loser = None;
+
+  for _ in 0..max_moves {
+    if position.legal_moves().is_empty() {
+      loser = Some(position.turn.to_owned());
+      break;
+    }
+
+    let policy = policy(&engine.search(&position, None)?.scores);
+    let location = sample_move(&policy, rng).ok_or(ErrorKind::InvalidData)?;
+    samples.push(Sample {
+      size_x: position.board.size_x,
+      size_y: position.board.size_y,
+      board: encode_board(&position),
+      turn: position.turn.to_owned(),
+      policy,
+      result: 0,
+    });
+
+    position.make_move(location)?;
+  }
+
+  if let Some(loser) = loser {
+    for sample in &mut samples {
+      sample.result = if sample.turn == loser { -1 } else { 1 };
+    }
+  }
+
+  Ok(samples)
+}
+
+/// Plays games from a position and writes their samples as JSON lines.
+/// Returns how many samples were written.
+pub fn run(start: &Position, engine: &Engine, games: usize, max_moves: usize, seed: u64, out: &mut impl Write) -> Result<usize, ErrorKind> {
+  let mut rng = Rng::new(seed);
+  let mut written = 0;
+
+  for game in 0..games {
+    let samples = play_game(start, engine, &mut rng, max_moves)?;
+    log!(Level::Debug, "selfplay", "game {} took {} moves", game, samples.len());
+
+    for sample in &samples {
+      writeln!(out, "{}", sample).map_err(|e| e.kind())?;
+    }
+    written += samples.len();
+  }
+
+  Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::engine::Engine;
+  use crate::network::Uniform;
+  use crate::position::{Position, Player};
+  use crate::selfplay::{self, Sample};
+  use crate::stress::Rng;
+  use crate::location::Location;
+
+  #[test]
+  fn test_play_game() {
+    let position = Position::from_diagram(". . .\n. . .\n. . .").unwrap();
+    let engine = Engine::new(&Uniform, 2);
+    let samples = selfplay::play_game(&position, &engine, &mut Rng::new(3), 100).unwrap();
+
+    // a small board fills up, and whoever is left without a move loses
+    let last = samples.last().unwrap();
+    assert_eq!(last.result, 1);
+    assert!(samples.iter().all(|i| i.result == if i.turn == last.turn { 1 } else { -1 }));
+    assert_eq!(samples[0].board, ".........");
+    assert!((samples[0].policy.iter().map(|i| i.1).sum::<f32>() - 1.0).abs() < 1e-5);
+
+    let mut out = Vec::new();
+    let written = selfplay::run(&Position::default(), &Engine::new(&Uniform, 1), 2, 10, 1, &mut out).unwrap();
+    assert_eq!(written, 20);
+    assert_eq!(String::from_utf8(out).unwrap().lines().count(), 20);
+
+    // a move that wins is played far more often than one that does not
+    let policy = selfplay::policy(&[(Location::new(1, 0), 0.0), (Location::new(0, 0), 1.0)]);
+    assert_eq!(policy[0].0, Location::new(0, 0));
+    assert!(policy[0].1 > 0.98 && (policy[0].1 + policy[1].1 - 1.0).abs() < 1e-6);
+
+    let sample = Sample {
+      size_x: 2,
+      size_y: 1,
+      board: "B.".to_owned(),
+      turn: Player::White,
+      policy: vec![(Location::new(1, 0), 1.0)],
+      result: 0,
+    };
+    assert_eq!(sample.to_string(), r#"{"size":[2,1],"board":"B.","turn":"white","policy":[[1,0,1]],"result":0}"#);
+  }
+}
@@ -0,0 +1,297 @@
+use std::collections::HashSet;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::board::{Board, BoardValue};
+use crate::location::Location;
+use crate::position::{Position, Player};
+use crate::record::GameRecord;
+use crate::rules::RuleSet;
+
+/// A small deterministic random number generator, so a stress run can be repeated from its seed.
+#[derive(Debug, Clone)]
+pub struct Rng {
+  state: u64,
+}
+
+impl Rng {
+  pub fn new(seed: u64) -> Self {
+    let mut rng = Self {
+      state: seed
+    };
+    rng.next_u64();
+    rng
+  }
+
+  pub fn next_u64(&mut self) -> u64 {
+    self.state = self.state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    let x = self.state;
+    (x ^ (x >> 33)).wrapping_mul(0xff51afd7ed558ccd) ^ (x >> 29)
+  }
+
+  /// Returns a number in `0..n`, `n` has to be more than zero.
+  pub fn below(&mut self, n: usize) -> usize {
+    (self.next_u64() % n as u64) as usize
+  }
+}
+
+/// A game in which the position stopped agreeing with a recomputation from scratch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+  /// The board and rules of the starting position.
+  pub board: Board,
+  pub rules: RuleSet,
+  /// The moves from the starting position, the last one being where it went wrong.
+  pub moves: Vec<Location>,
+  pub reason: String,
+}
+
+/// Shows the divergence as a game record with the reason as a comment,
+/// so it can be replayed by piping it into the game.
+impl std::fmt::Display for Divergence {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "# {}", self.reason)?;
+    let record = GameRecord {
+      board: self.board.clone(),
+      rules: self.rules.clone(),
+      moves: self.moves.clone(),
+      ..GameRecord::new()
+    };
+    write!(f, "{}", record)
+  }
+}
+
+/// Returns the legal moves of a player worked out from the board alone.
+fn expected_legal_moves(position: &Position, player: &Player) -> HashSet<Location> {
+  let groups = position.groups();
+  let mut result = HashSet::new();
+
+  for x in 0..position.board.size_x as isize {
+    for y in 0..position.board.size_y as isize {
+      let location = Location::new(x, y);
+      if position.board.get_at(&location) == Ok(&BoardValue::Empty) && (
+        position.is_anchor_square(&location, player) ||
+        groups.of_player(player).any(|i| i.liberties.contains(&location))
+      ) {
+        result.insert(location);
+      }
+    }
+  }
+
+  result
+}
+
+/// Returns the stones a move should capture, found by placing the stone without any
+/// bookkeeping and looking for groups of the opponent that are left without an anchor.
+fn expected_captures(position: &Position, location: &Location) -> HashSet<Location> {
+  let mut placed = position.clone();
+  if placed.board.insert_piece(&position.turn, location).is_err() {
+    return HashSet::new();
+  }
+
+  let opponent = position.turn.switch();
+  placed.groups()
+    .of_player(&opponent)
+    .filter(|i| !i.anchored)
+    .flat_map(|i| i.stones.iter().cloned())
+    .collect()
+}
+
+/// Plays a move and checks the captures, the legal moves and the chains against a
+/// recomputation from scratch. Returns what went wrong when they do not agree.
+pub fn check_move(position: &mut Position, location: &Location) -> Result<(), String> {
+  let captures = if position.rules().capture == crate::rules::Capture::Unanchored {
+    expected_captures(position, location)
+  } else {
+    HashSet::new()
+  };
+
+  // debug builds validate every move and panic when that fails, the panic hook
+  // still reports it, so callers that only want the divergence replace the hook
+  let outcome = match panic::catch_unwind(AssertUnwindSafe(|| position.make_move(location.to_owned()))) {
+    Ok(Ok(i)) => i,
+    Ok(Err(e)) => return Err(format!("{} was not accepted: {:?}", location, e)),
+    Err(e) => return Err(match e.downcast_ref::<String>() {
+      Some(i) => i.to_owned(),
+      None => "panicked".to_owned(),
+    }),
+  };
+
+  let captured: HashSet<Location> = outcome.captured_stones().cloned().collect();
+  if captured != captures {
+    return Err(format!("captured {} stones instead of {}", captured.len(), captures.len()));
+  }
+
+  if let Err(violations) = position.validate() {
+    let violations: Vec<String> = violations.iter().map(|i| i.to_string()).collect();
+    return Err(violations.join(", "));
+  }
+
+  for player in &[Player::Black, Player::White] {
+    let expected = expected_legal_moves(position, player);
+    let actual = position.legal_moves_for(player);
+    if let Some(i) = expected.symmetric_difference(actual).next() {
+      return Err(format!(
+        "{} should {}be a legal move for {:?}",
+        i, if expected.contains(i) { "" } else { "not " }, player
+      ));
+    }
+  }
+
+  Ok(())
+}
+
+/// Replays moves from a position, checking each of them.
+/// Returns `None` when a move turns out to be illegal, since then the game is not one to check,
+/// and otherwise what went wrong first together with the moves up to that point.
+fn replay(start: &Position, moves: &[Location]) -> Option<Result<(), Divergence>> {
+  let mut position = start.clone();
+
+  for (i, location) in moves.iter().enumerate() {
+    if !position.legal_moves().contains(location) {
+      return None;
+    }
+    if let Err(reason) = check_move(&mut position, location) {
+      return Some(Err(Divergence {
+        board: start.board.clone(),
+        rules: start.rules().clone(),
+        moves: moves[..=i].to_vec(),
+        reason,
+      }));
+    }
+  }
+
+  Some(Ok(()))
+}
+
+/// Removes moves from a failing game for as long as it keeps failing, first in large
+/// chunks and then one by one. Removing a single move hands all later moves to the other
+/// player, so at the end pairs of moves a player apart are tried as well.
+pub fn shrink(moves: &[Location], fails: impl Fn(&[Location]) -> bool) -> Vec<Location> {
+  let mut moves = moves.to_vec();
+  let mut chunk = (moves.len() / 2).max(1);
+
+  loop {
+    let mut i = 0;
+    while i + chunk <= moves.len() {
+      let mut candidate = moves.clone();
+      candidate.drain(i..i + chunk);
+
+      if fails(&candidate) {
+        moves = candidate;
+      } else {
+        i += 1;
+      }
+    }
+
+    if chunk == 1 {
+      break;
+    }
+    chunk /= 2;
+  }
+
+  let mut removed = true;
+  while removed {
+    removed = false;
+
+    'pairs: for i in 0..moves.len() {
+      for j in (i + 1..moves.len()).step_by(2) {
+        let mut candidate = moves.clone();
+        candidate.remove(j);
+        candidate.remove(i);
+
+        if fails(&candidate) {
+          moves = candidate;
+          removed = true;
+          break 'pairs;
+        }
+      }
+    }
+  }
+
+  moves
+}
+
+/// Plays random games from a position, checking every move, and returns how many
+/// moves were checked. On the first divergence the game is shrunk to the fewest
+/// moves that still go wrong, which is returned instead.
+pub fn run(start: &Position, games: usize, max_moves: usize, seed: u64) -> Result<usize, Box<Divergence>> {
+  let mut rng = Rng::new(seed);
+  let mut checked = 0;
+
+  let mut result = Ok(());
+  'games: for _ in 0..games {
+    let mut position = start.clone();
+    let mut moves = Vec::new();
+
+    for _ in 0..max_moves {
+      let mut legal: Vec<Location> = position.legal_moves().iter().cloned().collect();
+      if legal.is_empty() {
+        break;
+      }
+
+      // sorted first, the order of a set differs from run to run
+      legal.sort_by_key(|i| (i.x, i.y));
+      let location = legal[rng.below(legal.len())].to_owned();
+      moves.push(location.to_owned());
+      checked += 1;
+
+      if let Err(reason) = check_move(&mut position, &location) {
+        result = Err(Divergence {
+          board: start.board.clone(),
+          rules: start.rules().clone(),
+          moves,
+          reason,
+        });
+        break 'games;
+      }
+    }
+  }
+
+  result.map(|_| checked).map_err(|divergence| {
+    let moves = shrink(&divergence.moves, |i| matches!(replay(start, i), Some(Err(_))));
+    Box::new(match replay(start, &moves) {
+      Some(Err(i)) => i,
+      _ => divergence,
+    })
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::board::{Board, Topology};
+  use crate::location::Location;
+  use crate::position::Position;
+  use crate::rules::{Blocking, RuleSet};
+  use crate::stress::{self, Divergence, Rng};
+
+  #[test]
+  fn test_stress() {
+    assert!(stress::run(&Position::default(), 5, 60, 7).unwrap() > 0);
+
+    let rules = RuleSet { blocking: Blocking::Never, ..RuleSet::default() };
+    let board = Board::empty(9, 9).with_topology(Topology::Cylinder);
+    assert!(stress::run(&Position::new(board, rules).unwrap(), 2, 40, 3).unwrap() > 0);
+
+    let mut a = Rng::new(7);
+    let mut b = Rng::new(7);
+    assert!((0..10).all(|_| a.below(100) == b.below(100)));
+
+    let divergence = Divergence {
+      board: Board::empty(9, 9).with_topology(Topology::Cylinder),
+      rules: RuleSet { blocking: Blocking::Crossing, ..RuleSet::default() },
+      moves: vec![Location::new(0, 0)],
+      reason: "test".to_owned(),
+    };
+    assert_eq!(divergence.to_string(), "# test\nsize 9 9\ntopology cylinder\nblocking crossing\n0 0\n");
+  }
+
+  #[test]
+  fn test_shrink() {
+    let moves: Vec<Location> = (0..20).map(|i| Location::new(i, i % 3)).collect();
+    let fails = |moves: &[Location]| {
+      moves.contains(&Location::new(4, 1)) && moves.contains(&Location::new(15, 0))
+    };
+
+    assert_eq!(stress::shrink(&moves, fails), vec![Location::new(4, 1), Location::new(15, 0)]);
+  }
+}
@@ -0,0 +1,327 @@
+use std::fmt::Display;
+use std::io::ErrorKind;
+
+use crate::board::BoardValue;
+use crate::position::{Position, Player};
+use crate::location::Location;
+
+/// Distance in pixels between the centres of two neighbouring squares.
+const CELL: isize = 32;
+/// Empty space in pixels around the outermost squares, room for the coordinates.
+const MARGIN: isize = 36;
+
+/// Which parts of a position are drawn in an SVG.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SvgOptions {
+  /// Number the rows and columns along the top and left side.
+  pub coordinates: bool,
+  /// Mark the squares that are legal for black, white or both.
+  pub legal_moves: bool,
+  /// Draw the links between stones of the same colour.
+  pub links: bool,
+}
+
+impl Default for SvgOptions {
+  fn default() -> Self {
+    Self {
+      coordinates: true,
+      legal_moves: true,
+      links: true,
+    }
+  }
+}
+
+/// Displays a position as a standalone SVG document.
+pub struct Svg<'p> {
+  position: &'p Position,
+  options: SvgOptions,
+}
+
+impl<'p> Svg<'p> {
+  pub fn new(position: &'p Position) -> Self {
+    Self::with_options(position, SvgOptions::default())
+  }
+
+  pub fn with_options(position: &'p Position, options: SvgOptions) -> Self {
+    Self {
+      position,
+      options
+    }
+  }
+
+  /// Returns the pixel coordinates of the centre of a square.
+  fn centre(location: &Location) -> (isize, isize) {
+    (MARGIN + location.x * CELL, MARGIN + location.y * CELL)
+  }
+
+  /// Returns the pixel coordinates of a point in square coordinates, which may lie between squares.
+  fn point((x, y): (f64, f64)) -> (f64, f64) {
+    (MARGIN as f64 + x * CELL as f64, MARGIN as f64 + y * CELL as f64)
+  }
+
+  /// Returns the fill colour of a stone and the colour of its links.
+  fn colors(player: &Player) -> (&'static str, &'static str) {
+    match player {
+      Player::Black => ("#222222", "#222222"),
+      Player::White => ("#ffffff", "#f4f4f4"),
+    }
+  }
+
+  /// Returns the width and height in pixels of the picture of a position.
+  fn size(position: &Position) -> (isize, isize) {
+    (
+      MARGIN * 2 + (position.board.size_x as isize - 1) * CELL,
+      MARGIN * 2 + (position.board.size_y as isize - 1) * CELL,
+    )
+  }
+
+  fn write_header(f: &mut std::fmt::Formatter<'_>, position: &Position) -> std::fmt::Result {
+    let (width, height) = Svg::size(position);
+
+    writeln!(
+      f,
+      r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+      width, height
+    )?;
+    writeln!(f, r##"<rect width="100%" height="100%" fill="#e8c98f"/>"##)
+  }
+
+  /// Writes everything but the surrounding `<svg>` element,
+  /// so positions can be combined into a single document.
+  fn write_body(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let board = &self.position.board;
+
+    if self.options.coordinates {
+      writeln!(f, r##"<g font-family="sans-serif" font-size="11" fill="#5a4520" text-anchor="middle">"##)?;
+      for x in 0..board.size_x as isize {
+        let (cx, _) = Svg::centre(&Location::new(x, 0));
+        writeln!(f, r#"<text x="{}" y="{}">{}</text>"#, cx, MARGIN / 2, x)?;
+      }
+      for y in 0..board.size_y as isize {
+        let (_, cy) = Svg::centre(&Location::new(0, y));
+        writeln!(f, r#"<text x="{}" y="{}">{}</text>"#, MARGIN / 3, cy + 4, y)?;
+      }
+      writeln!(f, "</g>")?;
+    }
+
+    // holes are left out of the grid entirely
+    writeln!(f, r##"<g fill="#8a6d3b">"##)?;
+    for x in 0..board.size_x as isize {
+      for y in 0..board.size_y as isize {
+        let location = Location::new(x, y);
+        if board.get_at(&location) != Ok(&BoardValue::Blocked) {
+          let (cx, cy) = Svg::centre(&location);
+          writeln!(f, r#"<circle cx="{}" cy="{}" r="2"/>"#, cx, cy)?;
+        }
+      }
+    }
+    writeln!(f, "</g>")?;
+
+    if self.options.legal_moves {
+      writeln!(f, r#"<g class="legal" stroke-width="1.5">"#)?;
+      for x in 0..board.size_x as isize {
+        for y in 0..board.size_y as isize {
+          let location = Location::new(x, y);
+          let black = self.position.legal_black_moves.contains(&location);
+          let white = self.position.legal_white_moves.contains(&location);
+          let (cx, cy) = Svg::centre(&location);
+
+          // black only is a dark dot, white only a light dot and both a dot split in two
+          match (black, white) {
+            (true, true) => writeln!(
+              f,
+              r##"<path d="M{0} {1}a5 5 0 0 1 0 10z" fill="#222222"/><path d="M{0} {1}a5 5 0 0 0 0 10z" fill="#ffffff" stroke="#222222"/>"##,
+              cx, cy - 5
+            )?,
+            (true, false) => writeln!(f, r##"<circle cx="{}" cy="{}" r="5" fill="#222222"/>"##, cx, cy)?,
+            (false, true) => writeln!(f, r##"<circle cx="{}" cy="{}" r="5" fill="#ffffff" stroke="#222222"/>"##, cx, cy)?,
+            _ => (),
+          }
+        }
+      }
+      writeln!(f, "</g>")?;
+    }
+
+    if self.options.links {
+      for player in &[Player::Black, Player::White] {
+        let (_, link) = Svg::colors(player);
+        writeln!(
+          f,
+          r#"<g stroke="{}" stroke-width="5" stroke-linecap="round" class="links {:?}">"#,
+          link, player
+        )?;
+        for (from, to) in self.position.links(player) {
+          for (start, end) in board.link_segments(&from, &to) {
+            let (x1, y1) = Svg::point(start);
+            let (x2, y2) = Svg::point(end);
+            writeln!(f, r#"<line x1="{}" y1="{}" x2="{}" y2="{}"/>"#, x1, y1, x2, y2)?;
+          }
+        }
+        writeln!(f, "</g>")?;
+      }
+    }
+
+    for x in 0..board.size_x as isize {
+      for y in 0..board.size_y as isize {
+        let location = Location::new(x, y);
+        if board.get_at(&location) == Ok(&BoardValue::Neutral) {
+          let (cx, cy) = Svg::centre(&location);
+          writeln!(
+            f,
+            r##"<circle cx="{}" cy="{}" r="{}" fill="#9a9a9a" stroke="#222222" stroke-width="1.5"/>"##,
+            cx, cy, CELL * 2 / 5
+          )?;
+        }
+        for player in &[Player::Black, Player::White] {
+          if board.is_color_at(&location, player) {
+            let (cx, cy) = Svg::centre(&location);
+            let (fill, _) = Svg::colors(player);
+            writeln!(
+              f,
+              r##"<circle cx="{}" cy="{}" r="{}" fill="{}" stroke="#222222" stroke-width="1.5"/>"##,
+              cx, cy, CELL * 2 / 5, fill
+            )?;
+          }
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl<'p> Display for Svg<'p> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    Svg::write_header(f, self.position)?;
+    self.write_body(f)?;
+    write!(f, "</svg>")
+  }
+}
+
+/// Displays a sequence of positions as a single looping SVG animation,
+/// showing every position for the same amount of time.
+pub struct AnimatedSvg<'p> {
+  positions: &'p [Position],
+  options: SvgOptions,
+  seconds_per_frame: f64,
+}
+
+impl<'p> AnimatedSvg<'p> {
+  /// Fails with `InvalidInput` when there are no positions, an animation needs a first frame.
+  pub fn new(positions: &'p [Position], options: SvgOptions, seconds_per_frame: f64) -> Result<Self, ErrorKind> {
+    if positions.is_empty() {
+      return Err(ErrorKind::InvalidInput);
+    }
+
+    Ok(Self {
+      positions,
+      options,
+      seconds_per_frame
+    })
+  }
+}
+
+impl<'p> Display for AnimatedSvg<'p> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let first = &self.positions[0];
+    let frames = self.positions.len();
+    let duration = self.seconds_per_frame * frames as f64;
+
+    Svg::write_header(f, first)?;
+
+    for (i, position) in self.positions.iter().enumerate() {
+      // discrete animation: every frame is only visible during its own slice of the loop
+      let (values, key_times) = if i == 0 {
+        ("visible;hidden".to_owned(), format!("0;{}", 1.0 / frames as f64))
+      } else {
+        (
+          "hidden;visible;hidden".to_owned(),
+          format!("0;{};{}", i as f64 / frames as f64, (i + 1) as f64 / frames as f64)
+        )
+      };
+
+      writeln!(f, r#"<g visibility="hidden">"#)?;
+      writeln!(
+        f,
+        r#"<animate attributeName="visibility" calcMode="discrete" values="{}" keyTimes="{}" dur="{}s" repeatCount="indefinite"/>"#,
+        values, key_times, duration
+      )?;
+      Svg::with_options(position, self.options).write_body(f)?;
+      writeln!(f, "</g>")?;
+    }
+
+    write!(f, "</svg>")
+  }
+}
+
+/// Renders every position of a sequence as its own standalone SVG document.
+pub fn frames(positions: &[Position], options: SvgOptions) -> Vec<String> {
+  positions
+    .iter()
+    .map(|i| Svg::with_options(i, options).to_string())
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::board::{Board, Topology};
+  use crate::position::Position;
+  use crate::location::Location;
+  use crate::record::GameRecord;
+  use crate::rules::{Anchors, RuleSet};
+  use crate::svg::{self, AnimatedSvg, Svg, SvgOptions};
+
+  #[test]
+  fn test_svg_links() {
+    let mut position = Position::default();
+    position.make_move(Location::new(0, 0)).unwrap();
+    position.make_move(Location::new(12, 12)).unwrap();
+    position.make_move(Location::new(1, 2)).unwrap();
+
+    let output = position.to_svg().to_string();
+
+    assert!(output.starts_with("<svg"));
+    assert!(output.ends_with("</svg>"));
+    assert_eq!(output.matches("<line").count(), 1);
+    assert!(output.contains(r#"<line x1="36" y1="36" x2="68" y2="100"/>"#));
+    assert_eq!(output.matches("<text").count(), 26);
+
+    let options = SvgOptions { coordinates: false, legal_moves: false, links: false };
+    let bare = Svg::with_options(&position, options).to_string();
+    assert!(!bare.contains("<line"));
+    assert!(!bare.contains("<text"));
+    assert!(!bare.contains(r#"class="legal""#));
+  }
+
+  #[test]
+  fn test_svg_wrapped_link() {
+    let board = Board::default().with_topology(Topology::Cylinder);
+    let rules = RuleSet { anchors: Anchors::Rows(vec![0]), ..RuleSet::default() };
+    let mut position = Position::new(board, rules).unwrap();
+    position.make_move(Location::new(12, 0)).unwrap();
+    position.make_move(Location::new(5, 0)).unwrap();
+    position.make_move(Location::new(1, 1)).unwrap();
+
+    // the link runs off the right side from (12, 0) and comes back in on the left to (1, 1)
+    let output = position.to_svg().to_string();
+    assert_eq!(output.matches("<line").count(), 2);
+    assert!(output.contains(r#"<line x1="420" y1="36" x2="436" y2="44"/>"#));
+    assert!(output.contains(r#"<line x1="68" y1="68" x2="20" y2="44"/>"#));
+  }
+
+  #[test]
+  fn test_svg_game() {
+    let record: GameRecord = "0 0\n1 0\n2 1\n".parse().unwrap();
+    let positions = record.positions().unwrap();
+
+    let frames = svg::frames(&positions, SvgOptions::default());
+    assert_eq!(frames.len(), 4);
+    assert!(frames.iter().all(|i| i.starts_with("<svg") && i.ends_with("</svg>")));
+
+    let animation = AnimatedSvg::new(&positions, SvgOptions::default(), 1.0).unwrap().to_string();
+    assert_eq!(animation.matches("<svg").count(), 1);
+    assert_eq!(animation.matches("<animate ").count(), 4);
+    assert!(animation.contains(r#"keyTimes="0;0.75;1" dur="4s""#));
+    assert!(AnimatedSvg::new(&[], SvgOptions::default(), 1.0).is_err());
+  }
+}
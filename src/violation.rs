@@ -0,0 +1,46 @@
+use crate::location::Location;
+use crate::position::Player;
+
+/// Something wrong with a position, as found by `Position::validate`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+  /// The board has a different number of columns than its width says.
+  Width { size_x: usize, columns: usize },
+  /// A column of the board has a different number of squares than its height says.
+  Height { size_y: usize, x: usize, squares: usize },
+  /// A legal move is outside the board or on a square that is not empty.
+  IllegalSquare { player: Player, location: Location },
+  /// A stone is not part of any chain, or the chain is of the other player.
+  MissingChain { location: Location },
+  /// A chain holds a square without a stone of its player on it.
+  StaleChain { location: Location },
+  /// The stones linked to a stone are not the ones its chain says.
+  WrongChain { location: Location, expected: usize, found: usize },
+  /// A chain has no anchor, so it should have been captured.
+  Uncaptured { player: Player, location: Location },
+}
+
+impl std::fmt::Display for Violation {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Violation::Width { size_x, columns } => {
+        write!(f, "the board is {} wide but has {} columns", size_x, columns)
+      },
+      Violation::Height { size_y, x, squares } => {
+        write!(f, "the board is {} high but column {} has {} squares", size_y, x, squares)
+      },
+      Violation::IllegalSquare { player, location } => {
+        write!(f, "{} is a legal move for {:?} but not an empty square", location, player)
+      },
+      Violation::MissingChain { location } => write!(f, "the stone at {} has no chain", location),
+      Violation::StaleChain { location } => write!(f, "a chain holds {} but there is no stone of its player", location),
+      Violation::WrongChain { location, expected, found } => write!(
+        f, "the stone at {} is linked to a group of {} stones but its chain has {}",
+        location, expected, found
+      ),
+      Violation::Uncaptured { player, location } => {
+        write!(f, "the {:?} chain of {} has no anchor but was not captured", player, location)
+      },
+    }
+  }
+}